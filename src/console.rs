@@ -1,20 +1,163 @@
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicBool, Ordering};
+use windows::Win32::Foundation::{CloseHandle, GENERIC_READ, GENERIC_WRITE, HANDLE};
+use windows::Win32::Storage::FileSystem::{
+    CreateFileW, FILE_ATTRIBUTE_NORMAL, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+};
 use windows::Win32::System::Console::{
-    AllocConsole, CONSOLE_MODE, ENABLE_VIRTUAL_TERMINAL_PROCESSING, FreeConsole, GetConsoleMode,
-    GetStdHandle, STD_OUTPUT_HANDLE, SetConsoleMode,
+    ATTACH_PARENT_PROCESS, AllocConsole, AttachConsole, CONSOLE_MODE, CONSOLE_SCREEN_BUFFER_INFO,
+    COORD, ENABLE_VIRTUAL_TERMINAL_PROCESSING, FreeConsole, GetConsoleMode,
+    GetConsoleScreenBufferInfo, GetStdHandle, STD_ERROR_HANDLE, STD_HANDLE, STD_INPUT_HANDLE,
+    STD_OUTPUT_HANDLE, SetConsoleMode, SetConsoleScreenBufferSize, SetConsoleTitleW, SetStdHandle,
 };
+use windows::core::{HSTRING, PCWSTR, w};
 
 static CONSOLE_ALLOCATED: AtomicBool = AtomicBool::new(false);
+static CONSOLE_COLORS: AtomicBool = AtomicBool::new(false);
+/// The std handles from before [redirect_std_streams], restored by [free_console]. Stored as numbers, as `HANDLE`
+/// isn't `Send`.
+static PREVIOUS_STD_HANDLES: Mutex<Option<[usize; 3]>> = Mutex::new(None);
+
+const STD_HANDLES: [STD_HANDLE; 3] = [STD_OUTPUT_HANDLE, STD_ERROR_HANDLE, STD_INPUT_HANDLE];
+
+#[derive(Debug, Clone, Default)]
+pub struct ConsoleConfig {
+    /// The title of the console window.
+    pub title: Option<String>,
+    /// The width of the screen buffer in characters, keeps the current width if [None].
+    pub buffer_columns: Option<i16>,
+    /// The height of the screen buffer in lines, i.e. the scrollback. Keeps the current height if [None].
+    pub buffer_lines: Option<i16>,
+    /// Attach to the console of the parent process if it has one, instead of allocating a new one.
+    pub attach_to_parent: bool,
+}
 
-/// Allocate a Windows console.
+/// Allocate a Windows console, and redirect the std streams to it.
 pub fn alloc_console() -> eyre::Result<()> {
-    if !CONSOLE_ALLOCATED.swap(true, Ordering::SeqCst) {
-        unsafe { AllocConsole()? };
+    alloc_console_with(&ConsoleConfig::default())
+}
+
+/// Allocate (or attach to) a Windows console according to `config`, and redirect the std streams to it.
+pub fn alloc_console_with(config: &ConsoleConfig) -> eyre::Result<()> {
+    if CONSOLE_ALLOCATED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    let attached =
+        config.attach_to_parent && unsafe { AttachConsole(ATTACH_PARENT_PROCESS) }.is_ok();
+
+    if !attached && let Err(e) = unsafe { AllocConsole() } {
+        CONSOLE_ALLOCATED.store(false, Ordering::SeqCst);
+        return Err(e.into());
+    }
+
+    if let Err(e) = redirect_std_streams() {
+        // Don't leave a console behind which `is_console_allocated` doesn't know about.
+        let _ = free_console();
+        return Err(e);
+    }
+
+    unsafe {
+        if let Some(title) = &config.title {
+            SetConsoleTitleW(&HSTRING::from(title.as_str()))?;
+        }
+
+        if config.buffer_columns.is_some() || config.buffer_lines.is_some() {
+            let stdout_handle = GetStdHandle(STD_OUTPUT_HANDLE)?;
+            let mut info = CONSOLE_SCREEN_BUFFER_INFO::default();
+            GetConsoleScreenBufferInfo(stdout_handle, &mut info)?;
+
+            let size = COORD {
+                X: config.buffer_columns.unwrap_or(info.dwSize.X),
+                Y: config.buffer_lines.unwrap_or(info.dwSize.Y),
+            };
+            SetConsoleScreenBufferSize(stdout_handle, size)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Point the std output, error, and input handles to the current console.
+///
+/// A GUI process usually starts without valid std handles, and these aren't always updated by `AllocConsole`.
+/// The Rust std streams look up the handles on every use, so `println!` and `std::io::stdin` work afterwards.
+///
+/// If this fails partway the previous handles are restored.
+pub fn redirect_std_streams() -> eyre::Result<()> {
+    let mut previous = PREVIOUS_STD_HANDLES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let saved = previous.unwrap_or_else(current_std_handles);
+
+    let output = open_console_handle(w!("CONOUT$"))?;
+    let result = open_console_handle(w!("CONIN$")).and_then(|input| {
+        [output, output, input]
+            .into_iter()
+            .zip(STD_HANDLES)
+            .try_for_each(|(handle, kind)| unsafe { SetStdHandle(kind, handle) })
+            .inspect_err(|_| {
+                let _ = unsafe { CloseHandle(input) };
+            })
+    });
+
+    if let Err(e) = result {
+        set_std_handles(saved);
+        let _ = unsafe { CloseHandle(output) };
+        return Err(e.into());
     }
 
+    *previous = Some(saved);
+
     Ok(())
 }
 
+/// The returned handle is never closed while in use, as it is owned by the std handle table from then on.
+fn open_console_handle(name: PCWSTR) -> windows::core::Result<HANDLE> {
+    unsafe {
+        CreateFileW(
+            name,
+            (GENERIC_READ | GENERIC_WRITE).0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            None,
+            OPEN_EXISTING,
+            FILE_ATTRIBUTE_NORMAL,
+            None,
+        )
+    }
+}
+
+fn current_std_handles() -> [usize; 3] {
+    STD_HANDLES.map(|kind| unsafe { GetStdHandle(kind) }.map_or(0, |handle| handle.0 as usize))
+}
+
+fn set_std_handles(handles: [usize; 3]) {
+    for (kind, handle) in STD_HANDLES.into_iter().zip(handles) {
+        let _ = unsafe { SetStdHandle(kind, HANDLE(handle as *mut _)) };
+    }
+}
+
+/// Restore the std handles from before [redirect_std_streams], and close the console handles it opened.
+fn restore_std_handles() {
+    let Some(previous) = PREVIOUS_STD_HANDLES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+    else {
+        return;
+    };
+
+    let [output, _, input] = current_std_handles();
+    set_std_handles(previous);
+
+    // Output and error share the handle opened by `redirect_std_streams`.
+    for handle in [output, input] {
+        if handle != 0 && !previous.contains(&handle) {
+            let _ = unsafe { CloseHandle(HANDLE(handle as *mut _)) };
+        }
+    }
+}
+
 /// Enable console colors if the console is allocated.
 pub fn enable_console_colors() {
     if CONSOLE_ALLOCATED.load(Ordering::SeqCst) {
@@ -33,12 +176,26 @@ pub fn enable_console_colors() {
             // Call SetConsoleMode to set the new mode
             SetConsoleMode(stdout_handle, current_console_mode).unwrap();
         }
+
+        CONSOLE_COLORS.store(true, Ordering::SeqCst);
     }
 }
 
-/// Free the previously allocated Windows console.
+/// Whether a console was allocated with [alloc_console].
+pub fn is_console_allocated() -> bool {
+    CONSOLE_ALLOCATED.load(Ordering::SeqCst)
+}
+
+/// Whether ANSI colors were enabled with [enable_console_colors] for the current console.
+pub fn are_console_colors_enabled() -> bool {
+    is_console_allocated() && CONSOLE_COLORS.load(Ordering::SeqCst)
+}
+
+/// Free the previously allocated Windows console, restoring the std handles from before [redirect_std_streams].
 pub fn free_console() -> eyre::Result<()> {
     if CONSOLE_ALLOCATED.swap(false, Ordering::SeqCst) {
+        CONSOLE_COLORS.store(false, Ordering::SeqCst);
+        restore_std_handles();
         unsafe { FreeConsole()? };
     }
 
@@ -0,0 +1,675 @@
+//! Crash dumps for injected DLLs: a minidump and a text report are written when the game crashes.
+//!
+//! The text report names the faulting module and offset, and whether the fault lies within one of our detours,
+//! patches or our own DLL, which tells apart crashes caused by our hooks from those of the game itself. Patches are
+//! those of the patchers passed to [register_patcher](crate::proxying::lifecycle::register_patcher), detours are
+//! registered here with [register_detour].
+//!
+//! Both are written next to our own DLL (see [get_current_dll_path](crate::get_current_dll_path)) as
+//! `<dll name>_crash_<timestamp>.dmp` and `.txt`.
+//!
+//! Everything here is best effort, a crash due to e.g. heap corruption may prevent the report from being written.
+//! The files are written by a thread started on [install_crash_handler], the crashing thread only wakes it up and
+//! waits a bounded time for it, so nothing is allocated or spawned while handling the exception.
+//!
+//! # Example
+//! ```ignore
+//! fn attach() -> eyre::Result<()> {
+//!     crash_dump::install_crash_handler(Default::default())?;
+//!     lifecycle::register_patcher("fov", patcher.clone());
+//!
+//!     unsafe { MyHook.initialize(target, detour)?.enable()? };
+//!     crash_dump::register_detour("MyHook", target as *const (), MyHook.trampoline()?);
+//!     Ok(())
+//! }
+//! ```
+use std::ffi::c_void;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::os::windows::io::AsRawHandle;
+use std::os::windows::io::{FromRawHandle, OwnedHandle};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+
+use windows::Win32::Foundation::{
+    EXCEPTION_ACCESS_VIOLATION, EXCEPTION_ARRAY_BOUNDS_EXCEEDED, EXCEPTION_DATATYPE_MISALIGNMENT,
+    EXCEPTION_ILLEGAL_INSTRUCTION, EXCEPTION_IN_PAGE_ERROR, EXCEPTION_INT_DIVIDE_BY_ZERO,
+    EXCEPTION_PRIV_INSTRUCTION, EXCEPTION_STACK_OVERFLOW, HANDLE, NTSTATUS, STATUS_HEAP_CORRUPTION,
+    STATUS_STACK_BUFFER_OVERRUN, WAIT_OBJECT_0,
+};
+use windows::Win32::System::Diagnostics::Debug::{
+    AddVectoredExceptionHandler, EXCEPTION_CONTINUE_SEARCH, EXCEPTION_POINTERS,
+    LPTOP_LEVEL_EXCEPTION_FILTER, MINIDUMP_EXCEPTION_INFORMATION, MINIDUMP_TYPE,
+    MiniDumpWithDataSegs, MiniDumpWithIndirectlyReferencedMemory, MiniDumpWithThreadInfo,
+    MiniDumpWithUnloadedModules, MiniDumpWriteDump, RemoveVectoredExceptionHandler,
+    SetUnhandledExceptionFilter,
+};
+use windows::Win32::System::Threading::{
+    CreateEventW, GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId, INFINITE, ResetEvent,
+    SetEvent, WaitForSingleObject,
+};
+
+use crate::patching::process::{GameProcess, Module};
+use crate::proxying::lifecycle::PATCHERS;
+
+/// Approximate size of the jump written over the start of a detoured function.
+const DETOUR_PATCH_SIZE: usize = 16;
+/// Approximate size of a detour trampoline, the relocated prologue followed by a jump back.
+const TRAMPOLINE_SIZE: usize = 64;
+/// How long the crashing thread waits for the report and minidump to be written.
+const WRITE_TIMEOUT_MS: u32 = 30_000;
+/// How long [uninstall_crash_handler] waits for the writer thread to exit.
+const STOP_TIMEOUT_MS: u32 = 1_000;
+
+/// How the crash handler hooks into exception dispatching.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CrashHandlerMode {
+    /// `SetUnhandledExceptionFilter`, only exceptions nobody else handles are reported.
+    ///
+    /// The game (or its crash reporter) may replace the filter after us, in which case nothing is reported.
+    #[default]
+    UnhandledExceptionFilter,
+    /// `AddVectoredExceptionHandler`, fatal exception codes are reported before any other handler runs.
+    ///
+    /// This also reports exceptions which the game would handle itself, e.g. access violations probed for on
+    /// purpose, so use [CrashDumpConfig::max_dumps] to limit the damage.
+    Vectored,
+}
+
+#[derive(Debug, Clone)]
+pub struct CrashDumpConfig {
+    pub mode: CrashHandlerMode,
+    /// The folder to write to, defaults to the folder of our own DLL.
+    pub directory: Option<PathBuf>,
+    /// Whether to write a minidump besides the text report.
+    pub write_minidump: bool,
+    /// What the minidump contains, by default enough for stack traces and the values of locals.
+    pub dump_type: MINIDUMP_TYPE,
+    /// The maximum number of crashes to report for the lifetime of the process.
+    pub max_dumps: usize,
+}
+
+impl Default for CrashDumpConfig {
+    fn default() -> Self {
+        Self {
+            mode: CrashHandlerMode::default(),
+            directory: None,
+            write_minidump: true,
+            dump_type: MiniDumpWithDataSegs
+                | MiniDumpWithIndirectlyReferencedMemory
+                | MiniDumpWithThreadInfo
+                | MiniDumpWithUnloadedModules,
+            max_dumps: 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CrashHandler {
+    directory: PathBuf,
+    file_stem: String,
+    write_minidump: bool,
+    dump_type: MINIDUMP_TYPE,
+    /// The vectored handler, or the filter which was installed before ours.
+    installed: Installed,
+    writer: CrashWriter,
+}
+
+/// The thread writing the crash files, started ahead of time as the crashing thread can't safely spawn one.
+#[derive(Debug)]
+struct CrashWriter {
+    thread: JoinHandle<()>,
+    /// Signalled by the crashing thread once [PENDING_INFO] and [PENDING_THREAD] are set, or to stop the writer.
+    request: Arc<OwnedHandle>,
+    /// Signalled by the writer once it is done.
+    done: Arc<OwnedHandle>,
+    stop: Arc<AtomicBool>,
+}
+
+#[derive(Debug)]
+enum Installed {
+    Filter(LPTOP_LEVEL_EXCEPTION_FILTER),
+    Vectored(usize),
+}
+
+/// A named range of code which we either wrote or overwrote.
+#[derive(Debug, Clone)]
+struct CodeRegion {
+    name: String,
+    start: usize,
+    len: usize,
+    /// Whether `len` is a guess.
+    approximate: bool,
+}
+
+static HANDLER: RwLock<Option<CrashHandler>> = RwLock::new(None);
+static REGIONS: Mutex<Vec<CodeRegion>> = Mutex::new(Vec::new());
+static DUMPS_WRITTEN: AtomicUsize = AtomicUsize::new(0);
+static MAX_DUMPS: AtomicUsize = AtomicUsize::new(0);
+/// The crash for the writer thread to report.
+static PENDING_INFO: AtomicUsize = AtomicUsize::new(0);
+static PENDING_THREAD: AtomicU32 = AtomicU32::new(0);
+/// Set while a crash is being handled, further exceptions on other threads are ignored meanwhile.
+static HANDLING: AtomicBool = AtomicBool::new(false);
+
+/// Install the crash handler.
+///
+/// Calling it again replaces the previous configuration and handler.
+pub fn install_crash_handler(config: CrashDumpConfig) -> eyre::Result<()> {
+    let dll_path = crate::get_current_dll_path(crate::get_current_module()?)?;
+    let file_stem = dll_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "payload".into());
+    let directory = match config.directory {
+        Some(directory) => directory,
+        None => dll_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+
+    uninstall_crash_handler();
+
+    let writer = CrashWriter::spawn()?;
+    MAX_DUMPS.store(config.max_dumps, Ordering::SeqCst);
+
+    let installed = match config.mode {
+        CrashHandlerMode::UnhandledExceptionFilter => {
+            Installed::Filter(unsafe { SetUnhandledExceptionFilter(Some(unhandled_filter)) })
+        }
+        CrashHandlerMode::Vectored => {
+            let handle = unsafe { AddVectoredExceptionHandler(1, Some(vectored_handler)) };
+            if handle.is_null() {
+                writer.stop();
+                eyre::bail!("Failed to add the vectored exception handler");
+            }
+            Installed::Vectored(handle as usize)
+        }
+    };
+
+    *HANDLER.write().unwrap_or_else(|e| e.into_inner()) = Some(CrashHandler {
+        directory,
+        file_stem,
+        write_minidump: config.write_minidump,
+        dump_type: config.dump_type,
+        installed,
+        writer,
+    });
+
+    Ok(())
+}
+
+/// Remove the crash handler, e.g. before our DLL is unloaded, restoring the previous unhandled exception filter.
+///
+/// If another filter replaced ours in the meantime it is left in place. Should be called before our DLL is
+/// unloaded, but not from `DllMain`, as the writer thread can't exit while the loader lock is held.
+pub fn uninstall_crash_handler() {
+    let Some(handler) = HANDLER.write().unwrap_or_else(|e| e.into_inner()).take() else {
+        return;
+    };
+
+    match handler.installed {
+        Installed::Filter(previous) => unsafe {
+            // There is no way to query the current filter, other than replacing it.
+            let current = SetUnhandledExceptionFilter(previous);
+            let ours = unhandled_filter as unsafe extern "system" fn(_) -> _;
+            if !current.is_some_and(|current| std::ptr::fn_addr_eq(current, ours)) {
+                SetUnhandledExceptionFilter(current);
+            }
+        },
+        Installed::Vectored(handle) => unsafe {
+            RemoveVectoredExceptionHandler(handle as *mut c_void);
+        },
+    }
+
+    handler.writer.stop();
+}
+
+impl CrashWriter {
+    fn spawn() -> eyre::Result<Self> {
+        let request = Arc::new(unsafe { new_event()? });
+        let done = Arc::new(unsafe { new_event()? });
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let (thread_request, thread_done, thread_stop) =
+            (request.clone(), done.clone(), stop.clone());
+        let thread = std::thread::Builder::new()
+            .name("crash-dump".into())
+            .spawn(move || {
+                loop {
+                    let request = HANDLE(thread_request.as_raw_handle());
+                    if unsafe { WaitForSingleObject(request, INFINITE) } != WAIT_OBJECT_0
+                        || thread_stop.load(Ordering::SeqCst)
+                    {
+                        return;
+                    }
+
+                    let info = PENDING_INFO.load(Ordering::SeqCst) as *const EXCEPTION_POINTERS;
+                    write_crash(info, PENDING_THREAD.load(Ordering::SeqCst));
+
+                    let _ = unsafe { SetEvent(HANDLE(thread_done.as_raw_handle())) };
+                }
+            })?;
+
+        Ok(Self {
+            thread,
+            request,
+            done,
+            stop,
+        })
+    }
+
+    fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = unsafe { SetEvent(HANDLE(self.request.as_raw_handle())) };
+
+        // Bounded, joining would hang forever if we're called under the loader lock.
+        let thread = HANDLE(self.thread.as_raw_handle());
+        if unsafe { WaitForSingleObject(thread, STOP_TIMEOUT_MS) } == WAIT_OBJECT_0 {
+            let _ = self.thread.join();
+        }
+    }
+}
+
+/// An auto-reset event, initially not signalled.
+unsafe fn new_event() -> windows::core::Result<OwnedHandle> {
+    let event = CreateEventW(None, false, false, None)?;
+    Ok(OwnedHandle::from_raw_handle(event.0))
+}
+
+/// Register a detour, for reports to mention it when the fault lies within its patched `target` or `trampoline`.
+///
+/// The lengths of the patch and the trampoline aren't known, so fixed sizes are assumed and such matches are marked as
+/// approximate in the report. Use [register_code_region] instead if the exact ranges are known.
+pub fn register_detour(name: impl Into<String>, target: *const (), trampoline: &()) {
+    let name = name.into();
+    let mut regions = REGIONS.lock().unwrap_or_else(|e| e.into_inner());
+
+    regions.push(CodeRegion {
+        name: format!("detour `{}` (target)", name),
+        start: target as usize,
+        len: DETOUR_PATCH_SIZE,
+        approximate: true,
+    });
+    regions.push(CodeRegion {
+        name: format!("detour `{}` (trampoline)", name),
+        start: trampoline as *const () as usize,
+        len: TRAMPOLINE_SIZE,
+        approximate: true,
+    });
+}
+
+/// Register any other range of code, e.g. a code cave or generated code.
+pub fn register_code_region(name: impl Into<String>, start: *const u8, len: usize) {
+    REGIONS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(CodeRegion {
+            name: name.into(),
+            start: start as usize,
+            len,
+            approximate: false,
+        });
+}
+
+unsafe extern "system" fn unhandled_filter(info: *const EXCEPTION_POINTERS) -> i32 {
+    handle_crash(info);
+
+    // Let the previous filter run (e.g. Windows Error Reporting).
+    let previous = match HANDLER.try_read().as_deref() {
+        Ok(Some(CrashHandler {
+            installed: Installed::Filter(previous),
+            ..
+        })) => *previous,
+        _ => None,
+    };
+
+    match previous {
+        Some(previous) => previous(info),
+        None => EXCEPTION_CONTINUE_SEARCH,
+    }
+}
+
+unsafe extern "system" fn vectored_handler(info: *mut EXCEPTION_POINTERS) -> i32 {
+    // Called for every exception, including those the game handles itself, so bail out as early as possible.
+    if dumps_exhausted() {
+        return EXCEPTION_CONTINUE_SEARCH;
+    }
+
+    let code = (*(*info).ExceptionRecord).ExceptionCode;
+
+    if is_fatal(code) {
+        handle_crash(info);
+    }
+
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+fn is_fatal(code: NTSTATUS) -> bool {
+    [
+        EXCEPTION_ACCESS_VIOLATION,
+        EXCEPTION_ARRAY_BOUNDS_EXCEEDED,
+        EXCEPTION_DATATYPE_MISALIGNMENT,
+        EXCEPTION_ILLEGAL_INSTRUCTION,
+        EXCEPTION_IN_PAGE_ERROR,
+        EXCEPTION_INT_DIVIDE_BY_ZERO,
+        EXCEPTION_PRIV_INSTRUCTION,
+        EXCEPTION_STACK_OVERFLOW,
+        STATUS_HEAP_CORRUPTION,
+        STATUS_STACK_BUFFER_OVERRUN,
+    ]
+    .contains(&code)
+}
+
+fn dumps_exhausted() -> bool {
+    DUMPS_WRITTEN.load(Ordering::SeqCst) >= MAX_DUMPS.load(Ordering::SeqCst)
+}
+
+fn handle_crash(info: *const EXCEPTION_POINTERS) {
+    if dumps_exhausted() || HANDLING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    // Never block on the lock, the crash might have happened while it was held.
+    if let Ok(handler) = HANDLER.try_read()
+        && let Some(handler) = handler.as_ref()
+    {
+        PENDING_INFO.store(info as usize, Ordering::SeqCst);
+        PENDING_THREAD.store(unsafe { GetCurrentThreadId() }, Ordering::SeqCst);
+
+        // The crashing thread may have (almost) no stack left, e.g. after a stack overflow, and might hold the
+        // loader lock or a heap lock, so the files are written on the writer thread. The wait is bounded in case
+        // the writer needs any of those locks.
+        let done = HANDLE(handler.writer.done.as_raw_handle());
+        unsafe {
+            // A previous write which timed out may have signalled since.
+            let _ = ResetEvent(done);
+            if SetEvent(HANDLE(handler.writer.request.as_raw_handle())).is_ok() {
+                WaitForSingleObject(done, WRITE_TIMEOUT_MS);
+            }
+        }
+    }
+
+    HANDLING.store(false, Ordering::SeqCst);
+}
+
+fn write_crash(info: *const EXCEPTION_POINTERS, thread_id: u32) {
+    // Never block on the lock, the crash might have happened while it was held.
+    let Ok(handler) = HANDLER.try_read() else {
+        return;
+    };
+    let Some(handler) = handler.as_ref() else {
+        return;
+    };
+
+    if DUMPS_WRITTEN.fetch_add(1, Ordering::SeqCst) >= MAX_DUMPS.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let timestamp = crate::reporting::local_timestamp()
+        .replace(' ', "_")
+        .replace(':', "-");
+    let base_path = handler
+        .directory
+        .join(format!("{}_crash_{}", handler.file_stem, timestamp));
+
+    let report = unsafe { crash_report(&*info, thread_id) };
+    let report_path = base_path.with_extension("txt");
+    let mut summary = match std::fs::write(&report_path, &report) {
+        Ok(()) => format!("The game crashed, report written to {:?}", report_path),
+        Err(e) => format!(
+            "The game crashed, failed to write report {:?}: {}",
+            report_path, e
+        ),
+    };
+
+    if handler.write_minidump {
+        let dump_path = base_path.with_extension("dmp");
+        match write_minidump(&dump_path, info, thread_id, handler.dump_type) {
+            Ok(()) => summary.push_str(&format!(", minidump to {:?}", dump_path)),
+            Err(e) => summary.push_str(&format!(", failed to write minidump: {}", e)),
+        }
+    }
+
+    crate::reporting::report_warning(summary);
+}
+
+fn write_minidump(
+    path: &Path,
+    info: *const EXCEPTION_POINTERS,
+    thread_id: u32,
+    dump_type: MINIDUMP_TYPE,
+) -> eyre::Result<()> {
+    let file = File::create(path)?;
+    let exception = MINIDUMP_EXCEPTION_INFORMATION {
+        ThreadId: thread_id,
+        ExceptionPointers: info as *mut _,
+        ClientPointers: false.into(),
+    };
+
+    unsafe {
+        MiniDumpWriteDump(
+            GetCurrentProcess(),
+            GetCurrentProcessId(),
+            HANDLE(file.as_raw_handle()),
+            dump_type,
+            Some(&exception),
+            None,
+            None,
+        )?;
+    }
+
+    Ok(())
+}
+
+unsafe fn crash_report(info: &EXCEPTION_POINTERS, thread_id: u32) -> String {
+    let record = &*info.ExceptionRecord;
+    let address = record.ExceptionAddress as usize;
+    let mut report = String::new();
+
+    let _ = writeln!(report, "Crash at {}", crate::reporting::local_timestamp());
+    let _ = writeln!(
+        report,
+        "Exception: {:#010X} ({})",
+        record.ExceptionCode.0 as u32,
+        exception_name(record.ExceptionCode)
+    );
+    let _ = writeln!(report, "Thread: {}", thread_id);
+    let _ = writeln!(report, "Address: {:#X}", address);
+
+    if record.ExceptionCode == EXCEPTION_ACCESS_VIOLATION && record.NumberParameters >= 2 {
+        let operation = match record.ExceptionInformation[0] {
+            0 => "read from",
+            1 => "write to",
+            8 => "execute",
+            _ => "access",
+        };
+        let _ = writeln!(
+            report,
+            "Access violation: {} {:#X}",
+            operation, record.ExceptionInformation[1]
+        );
+    }
+
+    let modules = all_modules();
+    let _ = writeln!(report, "Module: {}", describe_address(&modules, address));
+
+    let our_module = crate::get_current_module()
+        .ok()
+        .map(|module| module.0 as usize);
+    let in_our_dll = modules
+        .iter()
+        .any(|module| Some(module.base() as usize) == our_module && contains(module, address));
+    let _ = writeln!(
+        report,
+        "Inside our DLL: {}",
+        if in_our_dll { "yes" } else { "no" }
+    );
+
+    let regions = matching_regions(address);
+    if regions.is_empty() {
+        let _ = writeln!(report, "Inside our detours or patches: no");
+    } else {
+        let _ = writeln!(
+            report,
+            "Inside our detours or patches: {}",
+            regions.join(", ")
+        );
+    }
+
+    if !info.ContextRecord.is_null() {
+        let _ = writeln!(report, "\nRegisters:");
+        write_registers(&mut report, &*info.ContextRecord);
+    }
+
+    let _ = writeln!(report, "\nModules:");
+    for module in &modules {
+        let _ = writeln!(
+            report,
+            "{:#018X} - {:#018X} {}",
+            module.base() as usize,
+            module.base() as usize + module.size(),
+            module.module_path().display()
+        );
+    }
+
+    report
+}
+
+/// The executable and all loaded modules of the current process.
+fn all_modules() -> Vec<Module> {
+    let process = GameProcess::current_process();
+    let mut modules = process.get_base_module().into_iter().collect::<Vec<_>>();
+
+    for module in process.get_modules().unwrap_or_default() {
+        if !modules.iter().any(|known| known.base() == module.base()) {
+            modules.push(module);
+        }
+    }
+
+    modules
+}
+
+fn contains(module: &Module, address: usize) -> bool {
+    let base = module.base() as usize;
+    address >= base && address - base < module.size()
+}
+
+fn describe_address(modules: &[Module], address: usize) -> String {
+    match modules.iter().find(|module| contains(module, address)) {
+        Some(module) => format!("{}+{:#X}", module.name(), address - module.base() as usize),
+        None => "<unknown, possibly generated code>".into(),
+    }
+}
+
+fn matching_regions(address: usize) -> Vec<String> {
+    let mut names = Vec::new();
+
+    if let Ok(regions) = REGIONS.try_lock() {
+        names.extend(
+            regions
+                .iter()
+                .filter(|region| address >= region.start && address - region.start < region.len)
+                .map(|region| {
+                    if region.approximate {
+                        format!("{} (approximate range)", region.name)
+                    } else {
+                        region.name.clone()
+                    }
+                }),
+        );
+    }
+
+    if let Ok(patchers) = PATCHERS.try_lock() {
+        for (name, patcher) in patchers.iter() {
+            let Ok(patcher) = patcher.try_lock() else {
+                continue;
+            };
+
+            for patch in patcher.patches() {
+                let start = patch.address as usize;
+                if address >= start && address - start < patch.patch_bytes.len() {
+                    names.push(format!("patch `{}` at {:#X}", name, start));
+                }
+            }
+        }
+    }
+
+    names
+}
+
+fn exception_name(code: NTSTATUS) -> &'static str {
+    match code {
+        EXCEPTION_ACCESS_VIOLATION => "access violation",
+        EXCEPTION_ARRAY_BOUNDS_EXCEEDED => "array bounds exceeded",
+        EXCEPTION_DATATYPE_MISALIGNMENT => "datatype misalignment",
+        EXCEPTION_ILLEGAL_INSTRUCTION => "illegal instruction",
+        EXCEPTION_IN_PAGE_ERROR => "in-page error",
+        EXCEPTION_INT_DIVIDE_BY_ZERO => "integer division by zero",
+        EXCEPTION_PRIV_INSTRUCTION => "privileged instruction",
+        EXCEPTION_STACK_OVERFLOW => "stack overflow",
+        STATUS_HEAP_CORRUPTION => "heap corruption",
+        STATUS_STACK_BUFFER_OVERRUN => "stack buffer overrun",
+        _ => "unknown exception",
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn write_registers(
+    report: &mut String,
+    context: &windows::Win32::System::Diagnostics::Debug::CONTEXT,
+) {
+    let registers = [
+        ("rax", context.Rax),
+        ("rbx", context.Rbx),
+        ("rcx", context.Rcx),
+        ("rdx", context.Rdx),
+        ("rsi", context.Rsi),
+        ("rdi", context.Rdi),
+        ("rbp", context.Rbp),
+        ("rsp", context.Rsp),
+        ("r8", context.R8),
+        ("r9", context.R9),
+        ("r10", context.R10),
+        ("r11", context.R11),
+        ("r12", context.R12),
+        ("r13", context.R13),
+        ("r14", context.R14),
+        ("r15", context.R15),
+        ("rip", context.Rip),
+    ];
+
+    for (name, value) in registers {
+        let _ = writeln!(report, "{:>3} = {:#018X}", name, value);
+    }
+}
+
+#[cfg(target_arch = "x86")]
+fn write_registers(
+    report: &mut String,
+    context: &windows::Win32::System::Diagnostics::Debug::CONTEXT,
+) {
+    let registers = [
+        ("eax", context.Eax),
+        ("ebx", context.Ebx),
+        ("ecx", context.Ecx),
+        ("edx", context.Edx),
+        ("esi", context.Esi),
+        ("edi", context.Edi),
+        ("ebp", context.Ebp),
+        ("esp", context.Esp),
+        ("eip", context.Eip),
+    ];
+
+    for (name, value) in registers {
+        let _ = writeln!(report, "{} = {:#010X}", name, value);
+    }
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "x86")))]
+fn write_registers(
+    report: &mut String,
+    _context: &windows::Win32::System::Diagnostics::Debug::CONTEXT,
+) {
+    let _ = writeln!(report, "<not supported on this architecture>");
+}
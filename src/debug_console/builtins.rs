@@ -0,0 +1,223 @@
+use std::fmt::Write;
+
+use crate::debug_console::{AddressExpr, ArgKind, Args, Command, CommandRegistry};
+use crate::patching::process::GameProcess;
+use crate::proxying::lifecycle::{HOOKS, PATCHERS};
+
+/// Bytes shown by `read` if no length is given.
+const DEFAULT_READ_LENGTH: u64 = 64;
+/// The most bytes `read` shows at once, the buffer is allocated up front.
+const MAX_READ_LENGTH: u64 = 4096;
+
+impl CommandRegistry {
+    /// A registry containing the built-in commands, see the [module](crate::debug_console) documentation.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+
+        registry
+            .register(
+                Command::new("read", "Hex dump memory of the current process", read)
+                    .arg("address", ArgKind::Address)
+                    .optional_arg("length", ArgKind::Uint),
+            )
+            .register(
+                Command::new(
+                    "write",
+                    "Write bytes to memory of the current process",
+                    write,
+                )
+                .arg("address", ArgKind::Address)
+                .arg("bytes", ArgKind::Bytes),
+            )
+            .register(Command::new("modules", "List the loaded modules", modules))
+            .register(Command::new(
+                "patches",
+                "List the registered patchers",
+                patches,
+            ))
+            .register(
+                Command::new("patch", "Enable or disable (or toggle) a patcher", patch)
+                    .arg("name", ArgKind::String)
+                    .optional_arg("enabled", ArgKind::Bool),
+            )
+            .register(Command::new("hooks", "List the registered hooks", hooks))
+            .register(
+                Command::new("hook", "Enable or disable (or toggle) a hook", hook)
+                    .arg("name", ArgKind::String)
+                    .optional_arg("enabled", ArgKind::Bool),
+            );
+
+        registry
+    }
+}
+
+fn resolve_address(address: &AddressExpr) -> eyre::Result<usize> {
+    match address {
+        AddressExpr::Absolute(address) => Ok(*address),
+        AddressExpr::ModuleOffset { module, offset } => {
+            let module = GameProcess::current_process().get_module(module)?;
+            Ok(module.base() as usize + offset)
+        }
+    }
+}
+
+fn read(args: &Args) -> eyre::Result<String> {
+    let address = resolve_address(args.address("address").unwrap())?;
+    let length = args.uint("length").unwrap_or(DEFAULT_READ_LENGTH);
+    if length > MAX_READ_LENGTH {
+        eyre::bail!(
+            "Can read at most {} bytes at once, got {}",
+            MAX_READ_LENGTH,
+            length
+        );
+    }
+
+    let mut buffer = vec![0u8; length as usize];
+    // SAFETY: `ReadProcessMemory` fails gracefully on invalid addresses.
+    let read = unsafe {
+        GameProcess::current_process().read_absolute_buffer(address as *mut u8, &mut buffer)?
+    };
+    buffer.truncate(read);
+
+    let mut output = String::new();
+    for (index, line) in buffer.chunks(16).enumerate() {
+        let _ = write!(output, "{:016X} ", address + index * 16);
+        for byte in line {
+            let _ = write!(output, " {:02X}", byte);
+        }
+        let padding = (16 - line.len()) * 3;
+        let ascii = line
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        let _ = writeln!(output, "{:padding$}  {}", "", ascii, padding = padding);
+    }
+
+    Ok(output.trim_end().into())
+}
+
+fn write(args: &Args) -> eyre::Result<String> {
+    let address = resolve_address(args.address("address").unwrap())?;
+    let bytes = args.bytes("bytes").unwrap();
+
+    // SAFETY: `WriteProcessMemory` fails gracefully on invalid addresses, what is written is up to the user.
+    unsafe {
+        GameProcess::current_process().write_absolute_buffer(address as *mut u8, bytes)?;
+    }
+
+    Ok(format!("Wrote {} bytes to {:#X}", bytes.len(), address))
+}
+
+fn modules(_: &Args) -> eyre::Result<String> {
+    let mut output = String::new();
+
+    for module in GameProcess::current_process().get_modules()? {
+        let _ = writeln!(
+            output,
+            "{:016X} {:>10X} {}",
+            module.base() as usize,
+            module.size(),
+            module.name()
+        );
+    }
+
+    Ok(output.trim_end().into())
+}
+
+fn patches(_: &Args) -> eyre::Result<String> {
+    let patchers = PATCHERS.lock().unwrap_or_else(|e| e.into_inner());
+    if patchers.is_empty() {
+        return Ok("No patchers registered".into());
+    }
+
+    let mut output = String::new();
+    for (name, patcher) in patchers.iter() {
+        let patcher = patcher.lock().unwrap_or_else(|e| e.into_inner());
+        let enabled = patcher
+            .patches()
+            .iter()
+            .all(|patch| unsafe { patch.is_enabled() });
+        let _ = writeln!(
+            output,
+            "{:<24} {:>3} patches {}",
+            name,
+            patcher.patches().len(),
+            if enabled { "enabled" } else { "disabled" }
+        );
+    }
+
+    Ok(output.trim_end().into())
+}
+
+fn patch(args: &Args) -> eyre::Result<String> {
+    let name = args.string("name").unwrap();
+    let patchers = PATCHERS.lock().unwrap_or_else(|e| e.into_inner());
+    let (name, patcher) = patchers
+        .iter()
+        .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+        .ok_or_else(|| eyre::eyre!("Unknown patcher `{}`", name))?;
+
+    let patcher = patcher.lock().unwrap_or_else(|e| e.into_inner());
+    // SAFETY: The patches were valid when they were applied, and registering a patcher makes them toggleable.
+    unsafe {
+        let enabled = args
+            .bool("enabled")
+            .unwrap_or_else(|| !patcher.patches().iter().all(|patch| patch.is_enabled()));
+
+        if enabled {
+            patcher.enable_all_patches();
+        } else {
+            patcher.disable_all_patches();
+        }
+
+        Ok(format!(
+            "{} `{}`",
+            if enabled { "Enabled" } else { "Disabled" },
+            name
+        ))
+    }
+}
+
+fn hooks(_: &Args) -> eyre::Result<String> {
+    let hooks = HOOKS.lock().unwrap_or_else(|e| e.into_inner());
+    if hooks.is_empty() {
+        return Ok("No hooks registered".into());
+    }
+
+    let mut output = String::new();
+    for (name, hook) in hooks.iter() {
+        let state = if hook.is_enabled() {
+            "enabled"
+        } else {
+            "disabled"
+        };
+        let _ = writeln!(output, "{:<24} {}", name, state);
+    }
+
+    Ok(output.trim_end().into())
+}
+
+fn hook(args: &Args) -> eyre::Result<String> {
+    let name = args.string("name").unwrap();
+    let hooks = HOOKS.lock().unwrap_or_else(|e| e.into_inner());
+    let (name, hook) = hooks
+        .iter()
+        .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+        .ok_or_else(|| eyre::eyre!("Unknown hook `{}`", name))?;
+
+    let enabled = args.bool("enabled").unwrap_or(!hook.is_enabled());
+    // SAFETY: Registering a hook makes it toggleable.
+    unsafe { hook.set_enabled(enabled)? };
+
+    Ok(format!(
+        "{} `{}`",
+        if enabled { "Enabled" } else { "Disabled" },
+        name
+    ))
+}
@@ -0,0 +1,69 @@
+//! An interactive debug console for injected DLLs, reading commands from stdin.
+//!
+//! Commands are registered in a [CommandRegistry] with typed arguments. Parsing and dispatching is free of any
+//! Windows APIs, the built-in commands and the REPL itself are only available on Windows.
+//!
+//! The built-in commands (`CommandRegistry::with_builtins`) are:
+//! * `read <address> [length]`/`write <address> <bytes>`: Read (up to 4096 bytes) or write memory of the current
+//!   process.
+//! * `modules`: List the loaded modules.
+//! * `patches`/`patch <name> [enabled]`: List and toggle patchers registered with
+//!   `proxying::lifecycle::register_patcher`.
+//! * `hooks`/`hook <name> [enabled]`: List and toggle hooks registered with `proxying::lifecycle::register_hook`.
+//!
+//! Addresses are either absolute (`0x7FF612340000`) or relative to a module (`game.exe+0x1234`).
+//!
+//! # Example
+//! ```ignore
+//! fn attach(hinst_dll: windows::Win32::Foundation::HMODULE) -> eyre::Result<()> {
+//!     rust_hooking_utils::console::alloc_console()?;
+//!
+//!     let mut registry = CommandRegistry::with_builtins();
+//!     registry.register(Command::new("god", "Toggle god mode", |args| {
+//!         Ok(format!("God mode: {}", args.bool("enabled").unwrap_or(true)))
+//!     }).optional_arg("enabled", ArgKind::Bool));
+//!
+//!     spawn_console(registry)?;
+//!     Ok(())
+//! }
+//! ```
+use thiserror::Error;
+
+pub use parser::{AddressExpr, ArgKind, ArgValue};
+pub use registry::{ArgSpec, Args, Command, CommandHandler, CommandRegistry};
+#[cfg(windows)]
+pub use repl::*;
+
+#[cfg(windows)]
+mod builtins;
+pub mod parser;
+pub mod registry;
+#[cfg(windows)]
+mod repl;
+
+pub type Result<T> = std::result::Result<T, CommandErrorKind>;
+
+#[derive(Debug, Error)]
+pub enum CommandErrorKind {
+    #[error("Unterminated quote")]
+    UnterminatedQuote,
+
+    #[error("Unknown command `{0}`, type `help` for a list of commands")]
+    UnknownCommand(String),
+
+    #[error("Missing argument `{arg}`, usage: {usage}")]
+    MissingArgument { arg: String, usage: String },
+
+    #[error("Too many arguments, usage: {usage}")]
+    TooManyArguments { usage: String },
+
+    #[error("Invalid value `{value}` for argument `{arg}`, expected {expected}")]
+    InvalidArgument {
+        arg: String,
+        value: String,
+        expected: ArgKind,
+    },
+
+    #[error(transparent)]
+    Any(#[from] eyre::Error),
+}
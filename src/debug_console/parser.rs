@@ -0,0 +1,280 @@
+//! Tokenizing of command lines, and parsing of typed arguments.
+use std::fmt::{Display, Formatter};
+
+use crate::debug_console::{CommandErrorKind, Result};
+
+/// The type of a command argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    /// A signed integer, decimal or hexadecimal with a `0x` prefix.
+    Int,
+    /// An unsigned integer, decimal or hexadecimal with a `0x` prefix.
+    Uint,
+    /// An address, either absolute hexadecimal (`0x7FF6...`, the prefix is optional) or relative to a module
+    /// (`game.exe+0x1234`).
+    Address,
+    Float,
+    /// `true`/`false`, `on`/`off`, `1`/`0`, or `yes`/`no`.
+    Bool,
+    String,
+    /// Hexadecimal bytes, optionally separated by spaces when quoted: `9090` or `"90 90"`.
+    Bytes,
+}
+
+impl Display for ArgKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ArgKind::Int => "int",
+            ArgKind::Uint => "uint",
+            ArgKind::Address => "address",
+            ArgKind::Float => "float",
+            ArgKind::Bool => "bool",
+            ArgKind::String => "string",
+            ArgKind::Bytes => "bytes",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// A parsed [ArgKind::Address].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressExpr {
+    Absolute(usize),
+    ModuleOffset { module: String, offset: usize },
+}
+
+impl Display for AddressExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressExpr::Absolute(address) => write!(f, "{:#X}", address),
+            AddressExpr::ModuleOffset { module, offset } => write!(f, "{}+{:#X}", module, offset),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgValue {
+    Int(i64),
+    Uint(u64),
+    Address(AddressExpr),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Bytes(Vec<u8>),
+}
+
+/// Split a command line into tokens on whitespace.
+///
+/// Tokens can be quoted with `"` to include whitespace, within quotes `\"` and `\\` are escapes.
+pub fn tokenize(line: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut token = String::new();
+
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(escaped @ ('"' | '\\')) => token.push(escaped),
+                        Some(other) => {
+                            token.push('\\');
+                            token.push(other);
+                        }
+                        None => return Err(CommandErrorKind::UnterminatedQuote),
+                    },
+                    Some(other) => token.push(other),
+                    None => return Err(CommandErrorKind::UnterminatedQuote),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+        }
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// Parse a single token as the given `kind`, returns [None] if it isn't valid.
+pub fn parse_value(token: &str, kind: ArgKind) -> Option<ArgValue> {
+    match kind {
+        ArgKind::Int => parse_int(token).map(ArgValue::Int),
+        ArgKind::Uint => parse_uint(token).map(ArgValue::Uint),
+        ArgKind::Address => parse_address(token).map(ArgValue::Address),
+        ArgKind::Float => token.parse().ok().map(ArgValue::Float),
+        ArgKind::Bool => match token.to_ascii_lowercase().as_str() {
+            "true" | "on" | "1" | "yes" => Some(ArgValue::Bool(true)),
+            "false" | "off" | "0" | "no" => Some(ArgValue::Bool(false)),
+            _ => None,
+        },
+        ArgKind::String => Some(ArgValue::String(token.into())),
+        ArgKind::Bytes => parse_bytes(token).map(ArgValue::Bytes),
+    }
+}
+
+fn parse_uint(token: &str) -> Option<u64> {
+    match strip_hex_prefix(token) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => token.parse().ok(),
+    }
+}
+
+fn parse_int(token: &str) -> Option<i64> {
+    match token.strip_prefix('-') {
+        Some(rest) => parse_uint(rest).and_then(|value| 0i64.checked_sub_unsigned(value)),
+        None => parse_uint(token).and_then(|value| i64::try_from(value).ok()),
+    }
+}
+
+fn parse_address(token: &str) -> Option<AddressExpr> {
+    let parse_hex =
+        |value: &str| usize::from_str_radix(strip_hex_prefix(value).unwrap_or(value), 16).ok();
+
+    match token.rsplit_once('+') {
+        Some((module, offset)) if !module.is_empty() => Some(AddressExpr::ModuleOffset {
+            module: module.into(),
+            offset: parse_hex(offset)?,
+        }),
+        _ => parse_hex(token).map(AddressExpr::Absolute),
+    }
+}
+
+fn parse_bytes(token: &str) -> Option<Vec<u8>> {
+    let digits = token
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<Vec<_>>();
+
+    if digits.is_empty() || digits.len() % 2 != 0 {
+        return None;
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let high = pair[0].to_digit(16)?;
+            let low = pair[1].to_digit(16)?;
+            Some((high * 16 + low) as u8)
+        })
+        .collect()
+}
+
+fn strip_hex_prefix(token: &str) -> Option<&str> {
+    token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_splits_on_whitespace() {
+        assert_eq!(
+            tokenize("  read\tgame.exe+0x10   32 ").unwrap(),
+            vec!["read", "game.exe+0x10", "32"]
+        );
+        assert!(tokenize("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn tokenize_handles_quotes_and_escapes() {
+        assert_eq!(
+            tokenize(r#"write 0x10 "90 90" "say \"hi\"" "a\\b" "c\d""#).unwrap(),
+            vec!["write", "0x10", "90 90", r#"say "hi""#, r"a\b", r"c\d"]
+        );
+        assert_eq!(tokenize(r#""""#).unwrap(), vec![""]);
+    }
+
+    #[test]
+    fn tokenize_rejects_unterminated_quotes() {
+        assert!(matches!(
+            tokenize(r#"say "hello"#),
+            Err(CommandErrorKind::UnterminatedQuote)
+        ));
+        assert!(matches!(
+            tokenize(r#"say "hello\"#),
+            Err(CommandErrorKind::UnterminatedQuote)
+        ));
+    }
+
+    #[test]
+    fn parses_integers() {
+        assert_eq!(parse_value("42", ArgKind::Uint), Some(ArgValue::Uint(42)));
+        assert_eq!(
+            parse_value("0xFF", ArgKind::Uint),
+            Some(ArgValue::Uint(255))
+        );
+        assert_eq!(
+            parse_value("0Xff", ArgKind::Uint),
+            Some(ArgValue::Uint(255))
+        );
+        assert_eq!(parse_value("-1", ArgKind::Uint), None);
+        assert_eq!(parse_value("-0x10", ArgKind::Int), Some(ArgValue::Int(-16)));
+        assert_eq!(
+            parse_value("-9223372036854775808", ArgKind::Int),
+            Some(ArgValue::Int(i64::MIN))
+        );
+        assert_eq!(parse_value("9223372036854775808", ArgKind::Int), None);
+        assert_eq!(parse_value("12abc", ArgKind::Int), None);
+    }
+
+    #[test]
+    fn parses_addresses() {
+        assert_eq!(
+            parse_value("0x7FF612340000", ArgKind::Address),
+            Some(ArgValue::Address(AddressExpr::Absolute(0x7FF6_1234_0000)))
+        );
+        assert_eq!(
+            parse_value("DEADBEEF", ArgKind::Address),
+            Some(ArgValue::Address(AddressExpr::Absolute(0xDEAD_BEEF)))
+        );
+        assert_eq!(
+            parse_value("game.exe+0x1234", ArgKind::Address),
+            Some(ArgValue::Address(AddressExpr::ModuleOffset {
+                module: "game.exe".into(),
+                offset: 0x1234,
+            }))
+        );
+        assert_eq!(parse_value("game.exe+xyz", ArgKind::Address), None);
+    }
+
+    #[test]
+    fn parses_bools_floats_and_bytes() {
+        assert_eq!(parse_value("ON", ArgKind::Bool), Some(ArgValue::Bool(true)));
+        assert_eq!(
+            parse_value("no", ArgKind::Bool),
+            Some(ArgValue::Bool(false))
+        );
+        assert_eq!(parse_value("maybe", ArgKind::Bool), None);
+        assert_eq!(
+            parse_value("1.5", ArgKind::Float),
+            Some(ArgValue::Float(1.5))
+        );
+        assert_eq!(
+            parse_value("90 e9 FF", ArgKind::Bytes),
+            Some(ArgValue::Bytes(vec![0x90, 0xE9, 0xFF]))
+        );
+        assert_eq!(parse_value("909", ArgKind::Bytes), None);
+        assert_eq!(parse_value("zz", ArgKind::Bytes), None);
+        assert_eq!(parse_value("", ArgKind::Bytes), None);
+    }
+}
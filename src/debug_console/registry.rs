@@ -0,0 +1,369 @@
+//! Registration and dispatching of console commands.
+use std::collections::BTreeMap;
+
+use crate::debug_console::parser::{AddressExpr, ArgKind, ArgValue, parse_value, tokenize};
+use crate::debug_console::{CommandErrorKind, Result};
+
+/// Called with the parsed arguments, the returned string is printed to the console.
+pub type CommandHandler = Box<dyn Fn(&Args) -> eyre::Result<String> + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub optional: bool,
+}
+
+/// A named command with typed arguments.
+///
+/// # Example
+/// ```ignore
+/// let command = Command::new("give", "Give the player an item", |args| {
+///     let count = args.uint("count").unwrap_or(1);
+///     Ok(format!("Gave {} x{}", args.string("item").unwrap(), count))
+/// })
+/// .arg("item", ArgKind::String)
+/// .optional_arg("count", ArgKind::Uint);
+/// ```
+pub struct Command {
+    name: String,
+    description: String,
+    args: Vec<ArgSpec>,
+    handler: CommandHandler,
+}
+
+impl Command {
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        handler: impl Fn(&Args) -> eyre::Result<String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            args: Vec::new(),
+            handler: Box::new(handler),
+        }
+    }
+
+    /// Add a required argument, required arguments can't follow optional ones.
+    pub fn arg(mut self, name: &'static str, kind: ArgKind) -> Self {
+        debug_assert!(
+            self.args.iter().all(|arg| !arg.optional),
+            "Required argument `{}` follows an optional one",
+            name
+        );
+        self.args.push(ArgSpec {
+            name,
+            kind,
+            optional: false,
+        });
+        self
+    }
+
+    /// Add an optional argument, it is absent from [Args] if not given.
+    pub fn optional_arg(mut self, name: &'static str, kind: ArgKind) -> Self {
+        self.args.push(ArgSpec {
+            name,
+            kind,
+            optional: true,
+        });
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn args(&self) -> &[ArgSpec] {
+        &self.args
+    }
+
+    /// The usage line, e.g. `read <address:address> [length:uint]`.
+    pub fn usage(&self) -> String {
+        let mut usage = self.name.clone();
+
+        for arg in &self.args {
+            let (open, close) = if arg.optional { ('[', ']') } else { ('<', '>') };
+            usage.push_str(&format!(" {}{}:{}{}", open, arg.name, arg.kind, close));
+        }
+
+        usage
+    }
+
+    /// Parse the given tokens (excluding the command name) according to the argument specification.
+    pub fn parse_args(&self, tokens: &[String]) -> Result<Args> {
+        if tokens.len() > self.args.len() {
+            return Err(CommandErrorKind::TooManyArguments {
+                usage: self.usage(),
+            });
+        }
+
+        let mut values = Vec::with_capacity(tokens.len());
+
+        for (index, spec) in self.args.iter().enumerate() {
+            let Some(token) = tokens.get(index) else {
+                if spec.optional {
+                    break;
+                }
+                return Err(CommandErrorKind::MissingArgument {
+                    arg: spec.name.into(),
+                    usage: self.usage(),
+                });
+            };
+
+            let value =
+                parse_value(token, spec.kind).ok_or_else(|| CommandErrorKind::InvalidArgument {
+                    arg: spec.name.into(),
+                    value: token.clone(),
+                    expected: spec.kind,
+                })?;
+
+            values.push((spec.name, value));
+        }
+
+        Ok(Args { values })
+    }
+}
+
+/// The parsed arguments passed to a [CommandHandler].
+///
+/// The typed getters return [None] if an optional argument was not given, or if the argument has a different kind.
+#[derive(Debug, Clone, Default)]
+pub struct Args {
+    values: Vec<(&'static str, ArgValue)>,
+}
+
+impl Args {
+    pub fn get(&self, name: &str) -> Option<&ArgValue> {
+        self.values
+            .iter()
+            .find(|(arg, _)| *arg == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn int(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            ArgValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn uint(&self, name: &str) -> Option<u64> {
+        match self.get(name)? {
+            ArgValue::Uint(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn address(&self, name: &str) -> Option<&AddressExpr> {
+        match self.get(name)? {
+            ArgValue::Address(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn float(&self, name: &str) -> Option<f64> {
+        match self.get(name)? {
+            ArgValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            ArgValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn string(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            ArgValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn bytes(&self, name: &str) -> Option<&[u8]> {
+        match self.get(name)? {
+            ArgValue::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// A set of commands, looked up case-insensitively by name.
+///
+/// `help` is always available and lists all commands, or shows the usage of a single one with `help <command>`.
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<String, Command>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a command, replacing any previous command with the same name.
+    pub fn register(&mut self, command: Command) -> &mut Self {
+        self.commands
+            .insert(command.name.to_ascii_lowercase(), command);
+        self
+    }
+
+    pub fn unregister(&mut self, name: &str) -> Option<Command> {
+        self.commands.remove(&name.to_ascii_lowercase())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.get(&name.to_ascii_lowercase())
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &Command> {
+        self.commands.values()
+    }
+
+    /// Parse and run a single command line, returning the output of the command.
+    ///
+    /// An empty line produces empty output.
+    pub fn execute(&self, line: &str) -> Result<String> {
+        let tokens = tokenize(line)?;
+        let Some((name, arg_tokens)) = tokens.split_first() else {
+            return Ok(String::new());
+        };
+
+        if name.eq_ignore_ascii_case("help") {
+            return self.help(arg_tokens.first().map(String::as_str));
+        }
+
+        let command = self
+            .get(name)
+            .ok_or_else(|| CommandErrorKind::UnknownCommand(name.clone()))?;
+        let args = command.parse_args(arg_tokens)?;
+
+        Ok((command.handler)(&args)?)
+    }
+
+    fn help(&self, command: Option<&str>) -> Result<String> {
+        match command {
+            Some(name) => {
+                let command = self
+                    .get(name)
+                    .ok_or_else(|| CommandErrorKind::UnknownCommand(name.into()))?;
+                Ok(format!("{}\n  {}", command.usage(), command.description))
+            }
+            None => {
+                let mut help = String::from("Commands:");
+                for command in self.commands() {
+                    help.push_str(&format!("\n  {:<12} {}", command.name, command.description));
+                }
+                help.push_str("\nType `help <command>` for its arguments.");
+                Ok(help)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> CommandRegistry {
+        let mut registry = CommandRegistry::new();
+        registry.register(
+            Command::new("Give", "Give the player an item", |args| {
+                let count = args.uint("count").unwrap_or(1);
+                Ok(format!("{} x{}", args.string("item").unwrap(), count))
+            })
+            .arg("item", ArgKind::String)
+            .optional_arg("count", ArgKind::Uint),
+        );
+        registry
+    }
+
+    #[test]
+    fn executes_case_insensitively() {
+        let registry = registry();
+
+        assert_eq!(registry.execute("give sword").unwrap(), "sword x1");
+        assert_eq!(
+            registry.execute("GIVE \"iron sword\" 0x10").unwrap(),
+            "iron sword x16"
+        );
+        assert_eq!(registry.execute("   ").unwrap(), "");
+    }
+
+    #[test]
+    fn rejects_unknown_commands() {
+        let registry = registry();
+
+        assert!(matches!(
+            registry.execute("take sword"),
+            Err(CommandErrorKind::UnknownCommand(name)) if name == "take"
+        ));
+        assert!(matches!(
+            registry.execute("help take"),
+            Err(CommandErrorKind::UnknownCommand(name)) if name == "take"
+        ));
+    }
+
+    #[test]
+    fn validates_arguments() {
+        let registry = registry();
+
+        assert!(matches!(
+            registry.execute("give"),
+            Err(CommandErrorKind::MissingArgument { arg, .. }) if arg == "item"
+        ));
+        assert!(matches!(
+            registry.execute("give sword 1 2"),
+            Err(CommandErrorKind::TooManyArguments { .. })
+        ));
+        assert!(matches!(
+            registry.execute("give sword many"),
+            Err(CommandErrorKind::InvalidArgument { arg, expected: ArgKind::Uint, .. }) if arg == "count"
+        ));
+    }
+
+    #[test]
+    fn help_lists_commands_and_usage() {
+        let registry = registry();
+
+        assert!(registry.execute("help").unwrap().contains("Give"));
+        assert_eq!(
+            registry.execute("help give").unwrap(),
+            "Give <item:string> [count:uint]\n  Give the player an item"
+        );
+    }
+
+    #[test]
+    fn register_replaces_and_unregister_removes() {
+        let mut registry = registry();
+        registry.register(Command::new("give", "Replaced", |_| Ok("replaced".into())));
+
+        assert_eq!(registry.execute("give").unwrap(), "replaced");
+        assert!(registry.unregister("GIVE").is_some());
+        assert!(registry.get("give").is_none());
+    }
+
+    #[test]
+    fn args_getters_check_the_kind() {
+        let command = Command::new("set", "", |_| Ok(String::new()))
+            .arg("value", ArgKind::Int)
+            .optional_arg("enabled", ArgKind::Bool);
+        let args = command.parse_args(&["-5".into()]).unwrap();
+
+        assert_eq!(args.int("value"), Some(-5));
+        assert_eq!(args.uint("value"), None);
+        assert!(!args.contains("enabled"));
+    }
+}
@@ -0,0 +1,51 @@
+use std::io::{BufRead, Write};
+use std::thread::JoinHandle;
+
+use crate::console::are_console_colors_enabled;
+use crate::debug_console::CommandRegistry;
+
+/// Read commands from stdin and execute them until `exit`/`quit` is entered or stdin is closed.
+///
+/// A console should have been allocated with [alloc_console](crate::console::alloc_console) beforehand.
+/// Errors of individual commands are printed and don't stop the console.
+pub fn run_console(registry: &CommandRegistry) -> eyre::Result<()> {
+    let stdin = std::io::stdin();
+    let mut line = String::new();
+
+    loop {
+        print!("> ");
+        std::io::stdout().flush()?;
+
+        line.clear();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        let command = line.trim();
+        if command.eq_ignore_ascii_case("exit") || command.eq_ignore_ascii_case("quit") {
+            return Ok(());
+        }
+
+        match registry.execute(command) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{}", output),
+            Err(e) if are_console_colors_enabled() => eprintln!("\x1b[31m{}\x1b[0m", e),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
+/// Run [run_console] on a new thread.
+///
+/// Reading from stdin blocks, so the thread only stops once the console is exited or closed.
+pub fn spawn_console(registry: CommandRegistry) -> eyre::Result<JoinHandle<()>> {
+    let handle = std::thread::Builder::new()
+        .name("debug-console".into())
+        .spawn(move || {
+            if let Err(e) = run_console(&registry) {
+                crate::reporting::report_error("run_console", &e);
+            }
+        })?;
+
+    Ok(handle)
+}
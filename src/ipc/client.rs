@@ -0,0 +1,193 @@
+//! The client side of the control channel, also re-exported from `launching::ipc`.
+//!
+//! It lives here rather than in `launching` so it builds, and is tested against the
+//! [IpcServer](crate::ipc::IpcServer), on every host.
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use crate::ipc::{
+    IpcEndpoint, IpcErrorKind, Request, Response, Result, read_message, write_message,
+};
+
+/// How long to wait between connection attempts while the server is not up yet.
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+enum Connection {
+    Pipe(File),
+    Tcp(TcpStream),
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Connection::Pipe(pipe) => pipe.read(buf),
+            Connection::Tcp(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Connection::Pipe(pipe) => pipe.write(buf),
+            Connection::Tcp(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Connection::Pipe(pipe) => pipe.flush(),
+            Connection::Tcp(stream) => stream.flush(),
+        }
+    }
+}
+
+/// A connection to an [IpcServer](crate::ipc::IpcServer), requests are answered in order.
+pub struct IpcClient {
+    connection: Connection,
+    endpoint: IpcEndpoint,
+}
+
+impl IpcClient {
+    /// Connect to the server at `endpoint`, retrying until `timeout` elapses.
+    ///
+    /// Retrying allows connecting right after injection, before the DLL has started its server.
+    pub fn connect(endpoint: &IpcEndpoint, timeout: Duration) -> Result<Self> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            match Self::try_connect(endpoint) {
+                Ok(connection) => {
+                    return Ok(Self {
+                        connection,
+                        endpoint: endpoint.clone(),
+                    });
+                }
+                Err(e) if Instant::now() < deadline && is_not_ready(&e) => {
+                    std::thread::sleep(RETRY_INTERVAL);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn try_connect(endpoint: &IpcEndpoint) -> std::io::Result<Connection> {
+        match endpoint {
+            IpcEndpoint::NamedPipe(name) => OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(crate::ipc::pipe_path(name))
+                .map(Connection::Pipe),
+            IpcEndpoint::Tcp(address) => {
+                let stream = TcpStream::connect(address)?;
+                stream.set_nodelay(true)?;
+                Ok(Connection::Tcp(stream))
+            }
+        }
+    }
+
+    pub fn endpoint(&self) -> &IpcEndpoint {
+        &self.endpoint
+    }
+
+    /// Send a request and wait for its response.
+    pub fn request(&mut self, request: &Request) -> Result<Response> {
+        write_message(&mut self.connection, request)?;
+        read_message(&mut self.connection)?.ok_or(IpcErrorKind::Disconnected)
+    }
+
+    /// Check the server is responsive.
+    pub fn ping(&mut self) -> Result<()> {
+        match self.request(&Request::Ping)? {
+            Response::Pong => Ok(()),
+            other => Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Expected a pong, got {:?}", other),
+            )
+            .into()),
+        }
+    }
+}
+
+fn is_not_ready(error: &std::io::Error) -> bool {
+    // `ERROR_PIPE_BUSY` while all pipe instances are in use.
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    matches!(
+        error.kind(),
+        ErrorKind::NotFound | ErrorKind::ConnectionRefused
+    ) || error.raw_os_error() == Some(ERROR_PIPE_BUSY)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{SocketAddr, TcpListener};
+
+    use super::*;
+    use crate::ipc::{IpcServer, IpcServerConfig};
+
+    fn tcp_config(address: SocketAddr) -> IpcServerConfig {
+        IpcServerConfig {
+            pipe_name: None,
+            tcp_fallback: Some(address),
+        }
+    }
+
+    /// A loopback address nothing is listening on (for now).
+    fn unused_address() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    #[test]
+    fn requests_and_pings() {
+        let server = IpcServer::start(&tcp_config(unused_address()), |request| match request {
+            Request::Command(line) => Response::Output(line.to_uppercase()),
+            _ => Response::Unsupported,
+        })
+        .unwrap();
+
+        let mut client = IpcClient::connect(server.endpoint(), Duration::from_secs(5)).unwrap();
+        assert_eq!(client.endpoint(), server.endpoint());
+
+        client.ping().unwrap();
+        assert_eq!(
+            client.request(&Request::Command("hello".into())).unwrap(),
+            Response::Output("HELLO".into())
+        );
+        assert_eq!(
+            client.request(&Request::ReloadConfig).unwrap(),
+            Response::Unsupported
+        );
+    }
+
+    #[test]
+    fn retries_until_the_server_is_up() {
+        let address = unused_address();
+        let client = std::thread::spawn(move || {
+            IpcClient::connect(&IpcEndpoint::Tcp(address), Duration::from_secs(10))
+        });
+
+        std::thread::sleep(RETRY_INTERVAL * 4);
+        let _server = IpcServer::start(&tcp_config(address), |_| Response::Ok).unwrap();
+
+        let mut client = client.join().unwrap().unwrap();
+        client.ping().unwrap();
+    }
+
+    #[test]
+    fn gives_up_after_the_timeout() {
+        let endpoint = IpcEndpoint::Tcp(unused_address());
+        let start = Instant::now();
+
+        assert!(matches!(
+            IpcClient::connect(&endpoint, Duration::from_millis(200)),
+            Err(IpcErrorKind::Io(e)) if e.kind() == ErrorKind::ConnectionRefused
+        ));
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
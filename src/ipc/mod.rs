@@ -0,0 +1,99 @@
+//! A local control channel for injected DLLs, allowing external tools to query state, toggle patches, etc.
+//!
+//! The injected DLL starts an [IpcServer], which listens on a named pipe and/or a loopback TCP socket. Messages are
+//! [Request]s and [Response]s, serialized with `bincode` and prefixed with their length. The matching [IpcClient]
+//! is also re-exported from `launching::ipc`.
+//!
+//! Named pipes are only available on Windows, on other hosts only the TCP endpoint is used.
+//!
+//! # Example
+//! ```ignore
+//! fn attach(hinst_dll: windows::Win32::Foundation::HMODULE) -> eyre::Result<()> {
+//!     let server = IpcServer::start(&IpcServerConfig::default(), |request| match request {
+//!         Request::ReloadConfig => {
+//!             reload_config();
+//!             Response::Ok
+//!         }
+//!         _ => Response::Unsupported,
+//!     })?;
+//!     // Keep the server running until the DLL is unloaded.
+//!     std::mem::forget(server);
+//!     Ok(())
+//! }
+//! ```
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+
+use thiserror::Error;
+
+pub use client::*;
+pub use protocol::*;
+pub use server::*;
+
+mod client;
+mod protocol;
+mod server;
+
+pub type Result<T> = std::result::Result<T, IpcErrorKind>;
+
+#[derive(Debug, Error)]
+pub enum IpcErrorKind {
+    #[error("Failed to encode a message: {0}")]
+    Encode(#[from] bincode::error::EncodeError),
+
+    #[error("Failed to decode a message: {0}")]
+    Decode(#[from] bincode::error::DecodeError),
+
+    #[error("Message of {0} bytes exceeds the maximum of {MAX_MESSAGE_SIZE} bytes")]
+    MessageTooLarge(usize),
+
+    #[error("The other side closed the connection")]
+    Disconnected,
+
+    #[error("No endpoint could be bound, neither a pipe name nor a TCP address was usable")]
+    NoEndpoint,
+
+    #[error("Refusing to listen on {0}, only loopback addresses are allowed")]
+    NonLoopbackAddress(SocketAddr),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[cfg(windows)]
+    #[error(transparent)]
+    OtherErr(#[from] windows::core::Error),
+}
+
+/// Where a server listens, or a client connects to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IpcEndpoint {
+    /// The name of a pipe, without the `\\.\pipe\` prefix. Windows only.
+    NamedPipe(String),
+    Tcp(SocketAddr),
+}
+
+impl IpcEndpoint {
+    /// The named pipe a server in the process with the given `pid` uses by default.
+    pub fn for_process(pid: u32) -> Self {
+        IpcEndpoint::NamedPipe(default_pipe_name(pid))
+    }
+}
+
+impl Display for IpcEndpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IpcEndpoint::NamedPipe(name) => write!(f, "{}", pipe_path(name)),
+            IpcEndpoint::Tcp(address) => write!(f, "tcp://{}", address),
+        }
+    }
+}
+
+/// The default pipe name for a server within the process with the given `pid`.
+pub fn default_pipe_name(pid: u32) -> String {
+    format!("rust_hooking_utils_{}", pid)
+}
+
+/// The full path of the pipe with the given `name`.
+pub fn pipe_path(name: &str) -> String {
+    format!(r"\\.\pipe\{}", name)
+}
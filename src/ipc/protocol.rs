@@ -0,0 +1,200 @@
+use std::io::{ErrorKind, Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::ipc::{IpcErrorKind, Result};
+
+/// The largest message which will be sent or accepted, guards against reading garbage lengths.
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Request {
+    /// Answered with [Response::Pong] by the server itself.
+    Ping,
+    /// Query a named piece of state, or all of it with [None].
+    GetState {
+        key: Option<String>,
+    },
+    /// Enable or disable a patch, [None] toggles it.
+    SetPatch {
+        name: String,
+        enabled: Option<bool>,
+    },
+    ReloadConfig,
+    /// A free-form command line, e.g. for the `CommandRegistry` of the debug console.
+    Command(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Response {
+    Pong,
+    /// Key-value pairs answering [Request::GetState].
+    State(Vec<(String, String)>),
+    Ok,
+    Output(String),
+    Error(String),
+    /// The handler does not support this request.
+    Unsupported,
+}
+
+/// Write a single length-prefixed message.
+pub fn write_message<T: Serialize>(writer: &mut impl Write, message: &T) -> Result<()> {
+    let bytes = bincode::serde::encode_to_vec(message, bincode::config::standard())?;
+    if bytes.len() > MAX_MESSAGE_SIZE {
+        return Err(IpcErrorKind::MessageTooLarge(bytes.len()));
+    }
+
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(&bytes)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Read a single length-prefixed message.
+///
+/// Returns [None] if the connection was closed before a new message started.
+pub fn read_message<T: DeserializeOwned>(reader: &mut impl Read) -> Result<Option<T>> {
+    let mut length = [0u8; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if is_disconnect(&e) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+
+    let length = u32::from_le_bytes(length) as usize;
+    if length > MAX_MESSAGE_SIZE {
+        return Err(IpcErrorKind::MessageTooLarge(length));
+    }
+
+    let mut bytes = vec![0u8; length];
+    reader.read_exact(&mut bytes).map_err(|e| {
+        if is_disconnect(&e) {
+            IpcErrorKind::Disconnected
+        } else {
+            e.into()
+        }
+    })?;
+
+    let (message, _) = bincode::serde::decode_from_slice(&bytes, bincode::config::standard())?;
+
+    Ok(Some(message))
+}
+
+fn is_disconnect(error: &std::io::Error) -> bool {
+    // A closed pipe reports `ERROR_BROKEN_PIPE` instead of an EOF.
+    matches!(
+        error.kind(),
+        ErrorKind::UnexpectedEof | ErrorKind::BrokenPipe | ErrorKind::ConnectionReset
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn round_trip<T: Serialize + DeserializeOwned>(message: &T) -> T {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, message).unwrap();
+
+        let length = u32::from_le_bytes(buffer[..4].try_into().unwrap()) as usize;
+        assert_eq!(length, buffer.len() - 4);
+
+        read_message(&mut Cursor::new(buffer)).unwrap().unwrap()
+    }
+
+    #[test]
+    fn requests_round_trip() {
+        let requests = [
+            Request::Ping,
+            Request::GetState { key: None },
+            Request::GetState {
+                key: Some("health".into()),
+            },
+            Request::SetPatch {
+                name: "fov".into(),
+                enabled: Some(false),
+            },
+            Request::SetPatch {
+                name: "fov".into(),
+                enabled: None,
+            },
+            Request::ReloadConfig,
+            Request::Command("read game.exe+0x10 \"32\"".into()),
+        ];
+
+        for request in requests {
+            assert_eq!(round_trip(&request), request);
+        }
+    }
+
+    #[test]
+    fn responses_round_trip() {
+        let responses = [
+            Response::Pong,
+            Response::State(vec![
+                ("health".into(), "100".into()),
+                ("ammo".into(), String::new()),
+            ]),
+            Response::Ok,
+            Response::Output("multi\nline".into()),
+            Response::Error("failed".into()),
+            Response::Unsupported,
+        ];
+
+        for response in responses {
+            assert_eq!(round_trip(&response), response);
+        }
+    }
+
+    #[test]
+    fn reads_consecutive_messages_until_eof() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &Request::Ping).unwrap();
+        write_message(&mut buffer, &Request::ReloadConfig).unwrap();
+
+        let mut reader = Cursor::new(buffer);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(Request::Ping));
+        assert_eq!(
+            read_message(&mut reader).unwrap(),
+            Some(Request::ReloadConfig)
+        );
+        assert_eq!(read_message::<Request>(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn truncated_message_is_a_disconnect() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &Request::Command("truncated".into())).unwrap();
+        buffer.truncate(buffer.len() - 1);
+
+        assert!(matches!(
+            read_message::<Request>(&mut Cursor::new(buffer)),
+            Err(IpcErrorKind::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn rejects_oversized_lengths() {
+        let buffer = ((MAX_MESSAGE_SIZE + 1) as u32).to_le_bytes().to_vec();
+
+        assert!(matches!(
+            read_message::<Request>(&mut Cursor::new(buffer)),
+            Err(IpcErrorKind::MessageTooLarge(length)) if length == MAX_MESSAGE_SIZE + 1
+        ));
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        let mut buffer = 4u32.to_le_bytes().to_vec();
+        buffer.extend([0xFF; 4]);
+
+        assert!(matches!(
+            read_message::<Request>(&mut Cursor::new(buffer)),
+            Err(IpcErrorKind::Decode(_))
+        ));
+    }
+}
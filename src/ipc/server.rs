@@ -0,0 +1,306 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+
+use crate::ipc::{
+    IpcEndpoint, IpcErrorKind, Request, Response, Result, read_message, write_message,
+};
+
+/// Handles every request but [Request::Ping], called on the thread of the respective client.
+pub type IpcHandler = Arc<dyn Fn(Request) -> Response + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct IpcServerConfig {
+    /// The named pipe to listen on, without the `\\.\pipe\` prefix. Ignored on hosts other than Windows.
+    ///
+    /// Defaults to [default_pipe_name](crate::ipc::default_pipe_name) of the current process.
+    pub pipe_name: Option<String>,
+    /// The loopback address to listen on if the named pipe could not be created, or [Self::pipe_name] is [None].
+    /// A port of `0` picks a free port, see [IpcServer::endpoint].
+    ///
+    /// Other addresses are rejected, as anyone who can connect is able to e.g. toggle patches.
+    pub tcp_fallback: Option<SocketAddr>,
+}
+
+impl Default for IpcServerConfig {
+    fn default() -> Self {
+        Self {
+            pipe_name: Some(crate::ipc::default_pipe_name(std::process::id())),
+            tcp_fallback: None,
+        }
+    }
+}
+
+/// A running server, accepting any number of clients on their own threads.
+///
+/// Dropping the server stops accepting new clients, already connected clients are served until they disconnect.
+pub struct IpcServer {
+    endpoint: IpcEndpoint,
+    stopping: Arc<AtomicBool>,
+    accept_thread: Option<JoinHandle<()>>,
+}
+
+impl IpcServer {
+    /// Bind the endpoint given by `config` and start accepting clients.
+    pub fn start(
+        config: &IpcServerConfig,
+        handler: impl Fn(Request) -> Response + Send + Sync + 'static,
+    ) -> Result<Self> {
+        if let Some(address) = config.tcp_fallback
+            && !address.ip().is_loopback()
+        {
+            return Err(IpcErrorKind::NonLoopbackAddress(address));
+        }
+
+        let handler: IpcHandler = Arc::new(handler);
+        let stopping = Arc::new(AtomicBool::new(false));
+
+        #[cfg(windows)]
+        if let Some(name) = &config.pipe_name {
+            match pipe::create_instance(name, true) {
+                Ok(first_instance) => {
+                    let accept_thread = spawn_named(
+                        "ipc-accept",
+                        pipe::accept_loop(name.clone(), first_instance, handler, stopping.clone()),
+                    )?;
+
+                    return Ok(Self {
+                        endpoint: IpcEndpoint::NamedPipe(name.clone()),
+                        stopping,
+                        accept_thread: Some(accept_thread),
+                    });
+                }
+                Err(e) if config.tcp_fallback.is_some() => {
+                    log::warn!(
+                        "Failed to create pipe `{}`, falling back to TCP: {}",
+                        name,
+                        e
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let address = config.tcp_fallback.ok_or(IpcErrorKind::NoEndpoint)?;
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+
+        let accept_stopping = stopping.clone();
+        let accept_thread = spawn_named("ipc-accept", move || {
+            for stream in listener.incoming() {
+                if accept_stopping.load(Ordering::Acquire) {
+                    break;
+                }
+
+                match stream {
+                    Ok(stream) => spawn_client(stream, handler.clone()),
+                    Err(e) => log::warn!("Failed to accept an IPC client: {}", e),
+                }
+            }
+        })?;
+
+        Ok(Self {
+            endpoint: IpcEndpoint::Tcp(address),
+            stopping,
+            accept_thread: Some(accept_thread),
+        })
+    }
+
+    /// The endpoint clients should connect to, for TCP this contains the actually bound port.
+    pub fn endpoint(&self) -> &IpcEndpoint {
+        &self.endpoint
+    }
+
+    /// Stop accepting new clients, equivalent to dropping the server.
+    pub fn stop(self) {}
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Release);
+
+        // Wake up the accept loop with a connection of our own.
+        let woken = match &self.endpoint {
+            IpcEndpoint::Tcp(address) => TcpStream::connect(address).is_ok(),
+            IpcEndpoint::NamedPipe(name) => std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(crate::ipc::pipe_path(name))
+                .is_ok(),
+        };
+
+        if let Some(accept_thread) = self.accept_thread.take()
+            && woken
+        {
+            let _ = accept_thread.join();
+        }
+    }
+}
+
+fn spawn_named(name: &str, f: impl FnOnce() + Send + 'static) -> Result<JoinHandle<()>> {
+    Ok(std::thread::Builder::new().name(name.into()).spawn(f)?)
+}
+
+fn spawn_client(stream: impl Read + Write + Send + 'static, handler: IpcHandler) {
+    if let Err(e) = spawn_named("ipc-client", move || serve_client(stream, handler)) {
+        log::error!("Failed to spawn an IPC client thread: {}", e);
+    }
+}
+
+fn serve_client(mut stream: impl Read + Write, handler: IpcHandler) {
+    loop {
+        let request = match read_message::<Request>(&mut stream) {
+            Ok(Some(request)) => request,
+            Ok(None) => return,
+            Err(e) => {
+                log::warn!("Failed to read an IPC request: {}", e);
+                return;
+            }
+        };
+
+        let response = match request {
+            Request::Ping => Response::Pong,
+            request => std::panic::catch_unwind(AssertUnwindSafe(|| handler(request)))
+                .unwrap_or_else(|_| Response::Error("The request handler panicked".into())),
+        };
+
+        if let Err(e) = write_message(&mut stream, &response) {
+            log::warn!("Failed to write an IPC response: {}", e);
+            return;
+        }
+    }
+}
+
+#[cfg(windows)]
+mod pipe {
+    use std::fs::File;
+    use std::os::windows::io::FromRawHandle;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use windows::Win32::Foundation::{ERROR_PIPE_CONNECTED, HANDLE};
+    use windows::Win32::Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX};
+    use windows::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+        PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+    use windows::core::HSTRING;
+
+    use crate::ipc::server::spawn_client;
+    use crate::ipc::{IpcHandler, Result};
+
+    const BUFFER_SIZE: u32 = 64 * 1024;
+
+    /// Create a new instance of the pipe, `first` fails if the pipe already exists (e.g. in another process).
+    pub(super) fn create_instance(name: &str, first: bool) -> Result<File> {
+        let mut open_mode = PIPE_ACCESS_DUPLEX;
+        if first {
+            open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+        }
+
+        let handle = unsafe {
+            CreateNamedPipeW(
+                &HSTRING::from(crate::ipc::pipe_path(name)),
+                open_mode,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                BUFFER_SIZE,
+                BUFFER_SIZE,
+                0,
+                None,
+            )
+        };
+
+        if handle.is_invalid() {
+            return Err(windows::core::Error::from_thread().into());
+        }
+
+        // SAFETY: We own the freshly created handle, the `File` closes it on drop.
+        Ok(unsafe { File::from_raw_handle(handle.0) })
+    }
+
+    pub(super) fn accept_loop(
+        name: String,
+        first_instance: File,
+        handler: IpcHandler,
+        stopping: Arc<AtomicBool>,
+    ) -> impl FnOnce() + Send + 'static {
+        move || {
+            let mut instance = first_instance;
+
+            loop {
+                let handle = HANDLE(std::os::windows::io::AsRawHandle::as_raw_handle(&instance));
+                let connected = match unsafe { ConnectNamedPipe(handle, None) } {
+                    Ok(()) => true,
+                    // The client connected between creating the instance and calling `ConnectNamedPipe`.
+                    Err(e) => e.code() == ERROR_PIPE_CONNECTED.to_hresult(),
+                };
+
+                if stopping.load(Ordering::Acquire) {
+                    return;
+                }
+
+                // Always create the next instance before serving, so clients don't see the pipe vanish.
+                let next = match create_instance(&name, false) {
+                    Ok(next) => next,
+                    Err(e) => {
+                        log::error!("Failed to create a new instance of pipe `{}`: {}", name, e);
+                        return;
+                    }
+                };
+
+                let current = std::mem::replace(&mut instance, next);
+                if connected {
+                    spawn_client(current, handler.clone());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tcp_config(address: &str) -> IpcServerConfig {
+        IpcServerConfig {
+            pipe_name: None,
+            tcp_fallback: Some(address.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn rejects_non_loopback_addresses() {
+        for address in ["0.0.0.0:0", "[::]:0", "192.168.1.2:0"] {
+            assert!(matches!(
+                IpcServer::start(&tcp_config(address), |_| Response::Ok),
+                Err(IpcErrorKind::NonLoopbackAddress(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn serves_requests_over_tcp() {
+        let server = IpcServer::start(&tcp_config("127.0.0.1:0"), |request| match request {
+            Request::Command(line) => Response::Output(line.to_uppercase()),
+            _ => Response::Unsupported,
+        })
+        .unwrap();
+        let IpcEndpoint::Tcp(address) = server.endpoint().clone() else {
+            panic!("Expected a TCP endpoint");
+        };
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        write_message(&mut stream, &Request::Ping).unwrap();
+        assert_eq!(read_message(&mut stream).unwrap(), Some(Response::Pong));
+
+        write_message(&mut stream, &Request::Command("hello".into())).unwrap();
+        assert_eq!(
+            read_message(&mut stream).unwrap(),
+            Some(Response::Output("HELLO".into()))
+        );
+    }
+}
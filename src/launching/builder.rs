@@ -0,0 +1,520 @@
+use std::ffi::{OsStr, OsString};
+use std::os::windows::ffi::OsStrExt;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use dll_syringe::process::{OwnedProcess, OwnedProcessModule, Process};
+use eyre::WrapErr;
+use windows::Win32::Foundation::{HANDLE, HANDLE_FLAG_INHERIT, SetHandleInformation};
+use windows::Win32::System::Console::{
+    GetStdHandle, STD_ERROR_HANDLE, STD_HANDLE, STD_INPUT_HANDLE, STD_OUTPUT_HANDLE,
+};
+use windows::Win32::System::Threading::{
+    CREATE_SUSPENDED, CREATE_UNICODE_ENVIRONMENT, CreateProcessW, PROCESS_CREATION_FLAGS,
+    PROCESS_INFORMATION, ResumeThread, STARTF_USESTDHANDLES, STARTUPINFOW,
+};
+use windows::core::{HSTRING, PCWSTR, PWSTR};
+
+use crate::launching::EnvironmentBlock;
+use crate::patching::exit::{ExitStatus, ExitWatcher};
+use crate::patching::process::BorrowedGameProcess;
+
+/// Where a standard stream of the launched process is connected to.
+#[derive(Debug, Default)]
+pub enum LaunchStdio {
+    /// Use the respective standard handle of the current process.
+    #[default]
+    Inherit,
+    /// Discard output, and read nothing as input.
+    Null,
+    /// Any handle, e.g. of a [File](std::fs::File) or one end of a pipe.
+    Handle(OwnedHandle),
+}
+
+impl From<std::fs::File> for LaunchStdio {
+    fn from(file: std::fs::File) -> Self {
+        LaunchStdio::Handle(file.into())
+    }
+}
+
+impl From<OwnedHandle> for LaunchStdio {
+    fn from(handle: OwnedHandle) -> Self {
+        LaunchStdio::Handle(handle)
+    }
+}
+
+/// A process launched by a [ProcessBuilder], owning the handles of the process and of its main thread.
+#[derive(Debug)]
+pub struct LaunchedProcess {
+    pub process: OwnedProcess,
+    pub main_thread: OwnedHandle,
+    pub pid: u32,
+    pub main_thread_id: u32,
+}
+
+impl LaunchedProcess {
+    /// Resume the main thread of a process launched with [ProcessBuilder::suspended].
+    pub fn resume(&self) -> eyre::Result<()> {
+        let previous_count = unsafe { ResumeThread(HANDLE(self.main_thread.as_raw_handle())) };
+        if previous_count == u32::MAX {
+            return Err(windows::core::Error::from_thread())
+                .wrap_err("Failed to resume the main thread");
+        }
+
+        Ok(())
+    }
+
+    /// The [GameProcess](crate::patching::process::GameProcess) view of the launched process, e.g. to read its memory.
+    pub fn game_process(&self) -> BorrowedGameProcess<'_> {
+        (&self.process).into()
+    }
+
+    /// Block until the process has exited, see [GameProcess::wait_for_exit](crate::patching::process::GameProcess::wait_for_exit).
+    pub fn wait_for_exit(&self, timeout: Option<Duration>) -> eyre::Result<Option<ExitStatus>> {
+        Ok(self.game_process().wait_for_exit(timeout)?)
+    }
+
+    /// The exit status, or [None] if the process is still running.
+    pub fn exit_status(&self) -> eyre::Result<Option<ExitStatus>> {
+        Ok(self.game_process().exit_status()?)
+    }
+
+    /// Call `on_exit` on a separate thread once the process exits, see [GameProcess::watch_exit](crate::patching::process::GameProcess::watch_exit).
+    pub fn watch_exit(
+        &self,
+        on_exit: impl FnOnce(ExitStatus) + Send + 'static,
+    ) -> eyre::Result<ExitWatcher> {
+        Ok(self.game_process().watch_exit(on_exit)?)
+    }
+
+    /// Close the main thread handle, keeping only the process.
+    pub fn into_process(self) -> OwnedProcess {
+        self.process
+    }
+}
+
+/// Builder for launching a process, similar to [std::process::Command] but exposing the raw handles.
+///
+/// # Example
+/// ```norun
+/// let launched = ProcessBuilder::new("C:/Games/Game/game.exe")
+///     .arg("-windowed")
+///     .env("DXVK_HUD", "fps")
+///     .stdout(File::create("game_stdout.txt")?)
+///     .suspended(true)
+///     .launch()?;
+/// ```
+#[derive(Debug)]
+pub struct ProcessBuilder {
+    exe_path: PathBuf,
+    args: Vec<OsString>,
+    current_dir: Option<PathBuf>,
+    environment: EnvironmentBlock,
+    /// The first invalid variable passed to [Self::env] or [Self::envs], returned by [Self::launch].
+    environment_error: Option<eyre::Report>,
+    creation_flags: PROCESS_CREATION_FLAGS,
+    stdin: LaunchStdio,
+    stdout: LaunchStdio,
+    stderr: LaunchStdio,
+}
+
+impl ProcessBuilder {
+    pub fn new(exe_path: impl Into<PathBuf>) -> Self {
+        Self {
+            exe_path: exe_path.into(),
+            args: Vec::new(),
+            current_dir: None,
+            environment: EnvironmentBlock::inherit(),
+            environment_error: None,
+            creation_flags: PROCESS_CREATION_FLAGS::default(),
+            stdin: LaunchStdio::Inherit,
+            stdout: LaunchStdio::Inherit,
+            stderr: LaunchStdio::Inherit,
+        }
+    }
+
+    /// Add an argument, it is quoted as needed.
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().into());
+        self
+    }
+
+    pub fn args(mut self, args: impl IntoIterator<Item = impl AsRef<OsStr>>) -> Self {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+        self
+    }
+
+    /// The working directory, defaults to the one of the current process.
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Set an environment variable, overriding the inherited value.
+    ///
+    /// An invalid variable (see [EnvironmentBlock::set]) makes [Self::launch] fail.
+    pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        if let Err(e) = self.environment.set(key, value) {
+            self.environment_error.get_or_insert(e);
+        }
+        self
+    }
+
+    pub fn envs(
+        mut self,
+        vars: impl IntoIterator<Item = (impl AsRef<OsStr>, impl AsRef<OsStr>)>,
+    ) -> Self {
+        if let Err(e) = self.environment.set_all(vars) {
+            self.environment_error.get_or_insert(e);
+        }
+        self
+    }
+
+    /// Remove an inherited environment variable.
+    pub fn env_remove(mut self, key: impl AsRef<OsStr>) -> Self {
+        self.environment.remove(key);
+        self
+    }
+
+    /// Don't inherit the environment of the current process, only variables set afterwards with [Self::env] are
+    /// passed.
+    pub fn env_clear(mut self) -> Self {
+        self.environment.clear();
+        self
+    }
+
+    /// Replace the whole environment, by default the environment of the current process is inherited.
+    pub fn environment(mut self, environment: EnvironmentBlock) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    /// Additional creation flags, `CREATE_UNICODE_ENVIRONMENT` is always set.
+    pub fn creation_flags(mut self, flags: PROCESS_CREATION_FLAGS) -> Self {
+        self.creation_flags |= flags;
+        self
+    }
+
+    /// Create the process with its main thread suspended, see [LaunchedProcess::resume].
+    pub fn suspended(mut self, suspended: bool) -> Self {
+        if suspended {
+            self.creation_flags |= CREATE_SUSPENDED;
+        } else {
+            self.creation_flags &= !CREATE_SUSPENDED;
+        }
+        self
+    }
+
+    pub fn stdin(mut self, stdin: impl Into<LaunchStdio>) -> Self {
+        self.stdin = stdin.into();
+        self
+    }
+
+    pub fn stdout(mut self, stdout: impl Into<LaunchStdio>) -> Self {
+        self.stdout = stdout.into();
+        self
+    }
+
+    pub fn stderr(mut self, stderr: impl Into<LaunchStdio>) -> Self {
+        self.stderr = stderr.into();
+        self
+    }
+
+    /// Create the process.
+    pub fn launch(self) -> eyre::Result<LaunchedProcess> {
+        if let Some(e) = self.environment_error {
+            return Err(e);
+        }
+
+        let mut command_line = build_command_line(self.exe_path.as_os_str(), &self.args);
+        let environment = self.environment.to_wide();
+        let exe_path = HSTRING::from(self.exe_path.as_path());
+        let current_dir = self.current_dir.as_deref().map(HSTRING::from);
+
+        let redirect = [&self.stdin, &self.stdout, &self.stderr]
+            .iter()
+            .any(|stdio| !matches!(stdio, LaunchStdio::Inherit));
+
+        // Kept alive until the process is created.
+        let mut null_handles = Vec::new();
+        let mut startup_info = STARTUPINFOW {
+            cb: size_of::<STARTUPINFOW>() as u32,
+            ..Default::default()
+        };
+
+        if redirect {
+            startup_info.dwFlags |= STARTF_USESTDHANDLES;
+            startup_info.hStdInput =
+                stdio_handle(&self.stdin, STD_INPUT_HANDLE, &mut null_handles)?;
+            startup_info.hStdOutput =
+                stdio_handle(&self.stdout, STD_OUTPUT_HANDLE, &mut null_handles)?;
+            startup_info.hStdError =
+                stdio_handle(&self.stderr, STD_ERROR_HANDLE, &mut null_handles)?;
+        }
+
+        let mut process_info = PROCESS_INFORMATION::default();
+
+        unsafe {
+            CreateProcessW(
+                &exe_path,
+                Some(PWSTR(command_line.as_mut_ptr())),
+                None,
+                None,
+                redirect,
+                CREATE_UNICODE_ENVIRONMENT | self.creation_flags,
+                Some(environment.as_ptr() as *const _),
+                current_dir
+                    .as_ref()
+                    .map_or(PCWSTR::null(), |dir| PCWSTR(dir.as_ptr())),
+                &startup_info,
+                &mut process_info,
+            )
+            .wrap_err_with(|| format!("Failed to create process {:?}", self.exe_path))?;
+
+            Ok(LaunchedProcess {
+                process: OwnedProcess::from_raw_handle(process_info.hProcess.0 as _),
+                main_thread: OwnedHandle::from_raw_handle(process_info.hThread.0),
+                pid: process_info.dwProcessId,
+                main_thread_id: process_info.dwThreadId,
+            })
+        }
+    }
+
+    /// Create the process suspended, inject the `payload_dll`, and only then resume the main thread.
+    ///
+    /// See [launch_and_inject](crate::launching::launch_and_inject).
+    pub fn launch_and_inject(
+        self,
+        payload_dll: &Path,
+    ) -> eyre::Result<(LaunchedProcess, OwnedProcessModule)> {
+        let launched = self.suspended(true).launch()?;
+
+        let injected = match crate::launching::injecting::inject_into_suspended_process(
+            &launched,
+            payload_dll,
+        ) {
+            Ok(injected) => injected,
+            Err(e) => {
+                let _ = launched.process.kill();
+                return Err(e);
+            }
+        };
+
+        // A process which stays suspended forever is of no use to anyone.
+        if let Err(e) = launched.resume() {
+            let _ = launched.process.kill();
+            return Err(e);
+        }
+
+        Ok((launched, injected))
+    }
+}
+
+/// Mark the handle as inheritable, or open the `NUL` device for [LaunchStdio::Null].
+fn stdio_handle(
+    stdio: &LaunchStdio,
+    std_handle: STD_HANDLE,
+    null_handles: &mut Vec<std::fs::File>,
+) -> eyre::Result<HANDLE> {
+    let handle = match stdio {
+        LaunchStdio::Inherit => {
+            // Failing to mark our own standard handle as inheritable (e.g. if there is none) is not fatal.
+            let handle = unsafe { GetStdHandle(std_handle)? };
+            if !handle.is_invalid() && !handle.0.is_null() {
+                let _ = unsafe {
+                    SetHandleInformation(handle, HANDLE_FLAG_INHERIT.0, HANDLE_FLAG_INHERIT)
+                };
+            }
+            return Ok(handle);
+        }
+        LaunchStdio::Null => {
+            let null = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open("NUL")?;
+            let handle = HANDLE(null.as_raw_handle());
+            null_handles.push(null);
+            handle
+        }
+        LaunchStdio::Handle(handle) => HANDLE(handle.as_raw_handle()),
+    };
+
+    unsafe { SetHandleInformation(handle, HANDLE_FLAG_INHERIT.0, HANDLE_FLAG_INHERIT)? };
+
+    Ok(handle)
+}
+
+/// Quote a single argument such that `CommandLineToArgvW` (and the MSVC runtime) parse it back unchanged.
+pub(crate) fn quote_arg(arg: &OsStr, out: &mut Vec<u16>) {
+    const QUOTE: u16 = b'"' as u16;
+    const BACKSLASH: u16 = b'\\' as u16;
+
+    let arg = arg.encode_wide().collect::<Vec<_>>();
+    let needs_quotes = arg.is_empty()
+        || arg
+            .iter()
+            .any(|&c| matches!(c, 0x20 | 0x09 | 0x0A | 0x0B | QUOTE));
+
+    if !needs_quotes {
+        out.extend_from_slice(&arg);
+        return;
+    }
+
+    out.push(QUOTE);
+
+    let mut backslashes = 0;
+    for &c in &arg {
+        match c {
+            BACKSLASH => backslashes += 1,
+            QUOTE => {
+                // Backslashes preceding a quote must be escaped, as must the quote itself.
+                out.extend(std::iter::repeat_n(BACKSLASH, backslashes * 2 + 1));
+                out.push(QUOTE);
+                backslashes = 0;
+            }
+            _ => {
+                out.extend(std::iter::repeat_n(BACKSLASH, backslashes));
+                out.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+
+    // Backslashes before the closing quote must be escaped.
+    out.extend(std::iter::repeat_n(BACKSLASH, backslashes * 2));
+    out.push(QUOTE);
+}
+
+/// The NUL terminated command line, the executable path followed by the quoted `args`.
+pub(crate) fn build_command_line(exe_path: &OsStr, args: &[OsString]) -> Vec<u16> {
+    let mut command_line = Vec::new();
+    quote_arg(exe_path, &mut command_line);
+
+    for arg in args {
+        command_line.push(b' ' as u16);
+        quote_arg(arg, &mut command_line);
+    }
+
+    command_line.push(0);
+    command_line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quoted(arg: &str) -> String {
+        let mut out = Vec::new();
+        quote_arg(OsStr::new(arg), &mut out);
+        String::from_utf16(&out).unwrap()
+    }
+
+    /// Split arguments (not the executable path) following the `CommandLineToArgvW` rules:
+    /// * `2n` backslashes followed by a quote become `n` backslashes, and the quote toggles quoting.
+    /// * `2n + 1` backslashes followed by a quote become `n` backslashes and a literal quote.
+    /// * Backslashes not followed by a quote are literal.
+    fn split_args(line: &str) -> Vec<String> {
+        let chars = line.chars().collect::<Vec<_>>();
+        let mut args = Vec::new();
+        let mut i = 0;
+
+        loop {
+            while i < chars.len() && matches!(chars[i], ' ' | '\t') {
+                i += 1;
+            }
+            if i == chars.len() {
+                return args;
+            }
+
+            let mut arg = String::new();
+            let mut in_quotes = false;
+
+            while i < chars.len() {
+                match chars[i] {
+                    '\\' => {
+                        let start = i;
+                        while i < chars.len() && chars[i] == '\\' {
+                            i += 1;
+                        }
+                        let count = i - start;
+
+                        if chars.get(i) == Some(&'"') {
+                            arg.extend(std::iter::repeat_n('\\', count / 2));
+                            if count % 2 == 1 {
+                                arg.push('"');
+                                i += 1;
+                            }
+                        } else {
+                            arg.extend(std::iter::repeat_n('\\', count));
+                        }
+                    }
+                    '"' => {
+                        in_quotes = !in_quotes;
+                        i += 1;
+                    }
+                    ' ' | '\t' if !in_quotes => break,
+                    c => {
+                        arg.push(c);
+                        i += 1;
+                    }
+                }
+            }
+
+            args.push(arg);
+        }
+    }
+
+    const CASES: &[(&str, &str)] = &[
+        ("", r#""""#),
+        ("plain", "plain"),
+        ("with space", r#""with space""#),
+        ("tab\there", "\"tab\there\""),
+        (r#"say "hi""#, r#""say \"hi\"""#),
+        (r"C:\path\", r"C:\path\"),
+        (r"C:\my path\", r#""C:\my path\\""#),
+        (r"C:\my path\\", r#""C:\my path\\\\""#),
+        (r#"a\"b"#, r#""a\\\"b""#),
+        (r#"a\\"b"#, r#""a\\\\\"b""#),
+        (r"a\\b c", r#""a\\b c""#),
+    ];
+
+    #[test]
+    fn quotes_arguments() {
+        for (arg, expected) in CASES {
+            assert_eq!(&quoted(arg), expected, "quoting {:?}", arg);
+        }
+    }
+
+    #[test]
+    fn quoted_arguments_parse_back_unchanged() {
+        for (arg, _) in CASES {
+            assert_eq!(split_args(&quoted(arg)), vec![*arg], "parsing {:?}", arg);
+        }
+    }
+
+    #[test]
+    fn builds_nul_terminated_command_line() {
+        let args = ["-windowed", "", "two words", r"trailing\"]
+            .map(OsString::from)
+            .to_vec();
+        let command_line = build_command_line(OsStr::new(r"C:\Program Files\Game\game.exe"), &args);
+
+        assert_eq!(command_line.last(), Some(&0));
+        let command_line = String::from_utf16(&command_line[..command_line.len() - 1]).unwrap();
+        assert_eq!(
+            command_line,
+            r#""C:\Program Files\Game\game.exe" -windowed "" "two words" trailing\"#
+        );
+
+        let parsed = split_args(&command_line);
+        assert_eq!(parsed[0], r"C:\Program Files\Game\game.exe");
+        assert_eq!(
+            parsed[1..],
+            args.iter()
+                .map(|arg| arg.to_str().unwrap())
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! Following the child processes of a launcher, and injecting into the ones which are the actual game.
+//!
+//! # Example
+//! ```norun
+//! let launcher = ProcessBuilder::new("C:/Games/Game/launcher.exe").launch()?;
+//! let results = ChildInjector::new(launcher.pid, "payload.dll")
+//!     .filter(ChildFilter::ExeName("game.exe".into()))
+//!     .run(Some(Duration::from_secs(60)))?;
+//! ```
+use std::collections::HashSet;
+use std::os::windows::io::AsRawHandle;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use dll_syringe::process::{OwnedProcess, OwnedProcessModule, Process};
+use windows::Wdk::System::Threading::{NtQueryInformationProcess, ProcessCommandLineInformation};
+use windows::Win32::Foundation::{HANDLE, UNICODE_STRING};
+
+use crate::launching::injecting::inject_into_running_process;
+use crate::launching::waiting::{as_game_process, wait_for_module};
+use crate::patching::discovery::{process_snapshot, wide_to_string};
+
+/// Which descendants of the parent process to inject into, see [ChildInjector::filter].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChildFilter {
+    /// The executable file name equals the given name, case-insensitively.
+    ExeName(String),
+    /// The command line contains the given string, case-insensitively.
+    CommandLine(String),
+}
+
+impl ChildFilter {
+    fn matches(&self, child: &ChildProcess) -> bool {
+        match self {
+            ChildFilter::ExeName(name) => child.exe_name.eq_ignore_ascii_case(name),
+            ChildFilter::CommandLine(needle) => child
+                .command_line
+                .as_ref()
+                .is_some_and(|line| line.to_lowercase().contains(&needle.to_lowercase())),
+        }
+    }
+}
+
+/// A descendant of the parent process which matched the filters.
+#[derive(Debug, Clone)]
+pub struct ChildProcess {
+    pub pid: u32,
+    pub parent_pid: u32,
+    pub exe_name: String,
+    /// [None] if the command line could not be read, e.g. due to missing access rights.
+    pub command_line: Option<String>,
+}
+
+/// The outcome of injecting into a single [ChildProcess].
+#[derive(Debug)]
+pub struct ChildInjection {
+    pub child: ChildProcess,
+    pub result: eyre::Result<OwnedProcessModule>,
+}
+
+/// Injects a payload into (transitive) child processes of a parent, e.g. a launcher which spawns the game.
+///
+/// Every matching process is injected into exactly once, no matter how often [ChildInjector::poll] is called.
+#[derive(Debug)]
+pub struct ChildInjector {
+    payload_dll: PathBuf,
+    filters: Vec<ChildFilter>,
+    recursive: bool,
+    poll_interval: Duration,
+    init_timeout: Duration,
+    /// The parent and all descendants found so far.
+    tracked: HashSet<u32>,
+    /// Descendants which have already been checked against the filters.
+    seen: HashSet<u32>,
+}
+
+impl ChildInjector {
+    /// Follow the children of the process with the given `parent_pid`, e.g. [LaunchedProcess::pid](crate::launching::LaunchedProcess::pid).
+    pub fn new(parent_pid: u32, payload_dll: impl Into<PathBuf>) -> Self {
+        Self {
+            payload_dll: payload_dll.into(),
+            filters: Vec::new(),
+            recursive: true,
+            poll_interval: Duration::from_millis(100),
+            init_timeout: Duration::from_secs(5),
+            tracked: HashSet::from([parent_pid]),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Only inject into children matching any of the added filters, by default all children are injected into.
+    pub fn filter(mut self, filter: ChildFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Whether to also follow children of children, enabled by default.
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// How long to wait for a new child to finish initialising (loading `kernel32.dll`) before injecting.
+    pub fn init_timeout(mut self, timeout: Duration) -> Self {
+        self.init_timeout = timeout;
+        self
+    }
+
+    /// Check for new children once, injecting into the ones matching the filters.
+    ///
+    /// # Returns
+    ///
+    /// The injections performed by this call.
+    pub fn poll(&mut self) -> eyre::Result<Vec<ChildInjection>> {
+        let snapshot = process_snapshot()?;
+        let mut found = Vec::new();
+
+        // Repeat until no new descendants are found, as a grandchild may precede its parent in the snapshot.
+        loop {
+            let new = snapshot
+                .iter()
+                .filter(|entry| !self.seen.contains(&entry.th32ProcessID))
+                .filter(|entry| self.tracked.contains(&entry.th32ParentProcessID))
+                .filter(|entry| !self.tracked.contains(&entry.th32ProcessID))
+                .collect::<Vec<_>>();
+
+            if new.is_empty() {
+                break;
+            }
+
+            for entry in new {
+                self.seen.insert(entry.th32ProcessID);
+                if self.recursive {
+                    self.tracked.insert(entry.th32ProcessID);
+                }
+                found.push(entry);
+            }
+        }
+
+        Ok(found
+            .into_iter()
+            .map(|entry| ChildProcess {
+                pid: entry.th32ProcessID,
+                parent_pid: entry.th32ParentProcessID,
+                exe_name: wide_to_string(&entry.szExeFile),
+                command_line: OwnedProcess::from_pid(entry.th32ProcessID)
+                    .ok()
+                    .and_then(|process| command_line(&process)),
+            })
+            .filter(|child| {
+                self.filters.is_empty() || self.filters.iter().any(|f| f.matches(child))
+            })
+            .map(|child| ChildInjection {
+                result: self.inject(child.pid),
+                child,
+            })
+            .collect())
+    }
+
+    /// Keep polling until the parent and all its followed descendants have exited, or the `timeout` elapsed.
+    ///
+    /// A `timeout` of [None] waits indefinitely.
+    ///
+    /// # Returns
+    ///
+    /// All injections performed, also the failed ones.
+    pub fn run(&mut self, timeout: Option<Duration>) -> eyre::Result<Vec<ChildInjection>> {
+        let start = Instant::now();
+        let timeout = timeout.unwrap_or(Duration::MAX);
+        let mut results = Vec::new();
+
+        loop {
+            results.extend(self.poll()?);
+
+            let alive = process_snapshot()?
+                .iter()
+                .any(|entry| self.tracked.contains(&entry.th32ProcessID));
+
+            if !alive || start.elapsed() > timeout {
+                break Ok(results);
+            }
+            std::thread::sleep(self.poll_interval);
+        }
+    }
+
+    fn inject(&self, pid: u32) -> eyre::Result<OwnedProcessModule> {
+        let process = OwnedProcess::from_pid(pid)?;
+
+        // Module enumeration (and thus injection) fails until the loader has initialised the process.
+        wait_for_module(
+            as_game_process(&process),
+            "kernel32.dll",
+            Some(self.init_timeout),
+        )?;
+
+        inject_into_running_process(process.borrowed(), &self.payload_dll)
+    }
+}
+
+/// Read the command line of the given `process`.
+pub fn command_line(process: &OwnedProcess) -> Option<String> {
+    let handle = HANDLE(process.as_raw_handle());
+    let mut length = 0;
+
+    // The first call only determines the required length.
+    unsafe {
+        let _ = NtQueryInformationProcess(
+            handle,
+            ProcessCommandLineInformation,
+            std::ptr::null_mut(),
+            0,
+            &mut length,
+        );
+    }
+    if length == 0 {
+        return None;
+    }
+
+    // `u64`s to properly align the `UNICODE_STRING` at the start of the buffer.
+    let mut buffer = vec![0u64; (length as usize).div_ceil(8)];
+    let status = unsafe {
+        NtQueryInformationProcess(
+            handle,
+            ProcessCommandLineInformation,
+            buffer.as_mut_ptr().cast(),
+            length,
+            &mut length,
+        )
+    };
+    if status.is_err() {
+        return None;
+    }
+
+    // The string points into `buffer`, directly after the header.
+    let string = unsafe { &*buffer.as_ptr().cast::<UNICODE_STRING>() };
+    if string.Buffer.is_null() {
+        return Some(String::new());
+    }
+    let wide = unsafe { std::slice::from_raw_parts(string.Buffer.0, string.Length as usize / 2) };
+
+    Some(String::from_utf16_lossy(wide))
+}
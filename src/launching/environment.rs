@@ -0,0 +1,210 @@
+use std::ffi::{OsStr, OsString};
+use std::os::windows::ffi::OsStrExt;
+
+/// The environment variables of a process to launch, see [ProcessBuilder::environment](crate::launching::ProcessBuilder::environment).
+///
+/// Variable names are case-insensitive like on Windows, setting `Path` replaces an inherited `PATH`.
+///
+/// # Example
+/// ```ignore
+/// let mut env = EnvironmentBlock::inherit();
+/// env.set("DXVK_HUD", "fps")?.remove("STEAM_COMPAT_DATA_PATH");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvironmentBlock {
+    vars: Vec<(OsString, OsString)>,
+}
+
+impl EnvironmentBlock {
+    /// An empty environment.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The environment of the current process.
+    pub fn inherit() -> Self {
+        let mut block = Self::new();
+        // Also keeps the hidden per-drive variables like `=C:`, which `set` accepts as well.
+        for (key, value) in std::env::vars_os() {
+            block.insert(key, value);
+        }
+        block
+    }
+
+    /// Set a variable, replacing any existing variable with the same (case-insensitive) name.
+    ///
+    /// Fails if the name is empty or contains a `=` (other than as its first character) or a NUL, or if the value
+    /// contains a NUL, as either would corrupt the block.
+    pub fn set(
+        &mut self,
+        key: impl AsRef<OsStr>,
+        value: impl AsRef<OsStr>,
+    ) -> eyre::Result<&mut Self> {
+        let (key, value) = (key.as_ref(), value.as_ref());
+
+        let key_chars = key.encode_wide().collect::<Vec<_>>();
+        if key_chars.is_empty() || key_chars.contains(&0) || key_chars[1..].contains(&(b'=' as u16))
+        {
+            eyre::bail!("Invalid environment variable name {:?}", key);
+        }
+        if value.encode_wide().any(|c| c == 0) {
+            eyre::bail!("The value of environment variable {:?} contains a NUL", key);
+        }
+
+        self.insert(key.into(), value.into());
+        Ok(self)
+    }
+
+    /// Set all given variables, see [Self::set].
+    ///
+    /// Variables preceding an invalid one are still set.
+    pub fn set_all(
+        &mut self,
+        vars: impl IntoIterator<Item = (impl AsRef<OsStr>, impl AsRef<OsStr>)>,
+    ) -> eyre::Result<&mut Self> {
+        for (key, value) in vars {
+            self.set(key, value)?;
+        }
+        Ok(self)
+    }
+
+    pub fn remove(&mut self, key: impl AsRef<OsStr>) -> &mut Self {
+        if let Some(index) = self.position(key.as_ref()) {
+            self.vars.remove(index);
+        }
+        self
+    }
+
+    pub fn clear(&mut self) -> &mut Self {
+        self.vars.clear();
+        self
+    }
+
+    pub fn get(&self, key: impl AsRef<OsStr>) -> Option<&OsStr> {
+        self.position(key.as_ref())
+            .map(|index| self.vars[index].1.as_os_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.vars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&OsStr, &OsStr)> {
+        self.vars
+            .iter()
+            .map(|(key, value)| (key.as_os_str(), value.as_os_str()))
+    }
+
+    /// The block as expected by `CreateProcessW` with `CREATE_UNICODE_ENVIRONMENT`.
+    ///
+    /// Entries are `key=value`, each terminated by a NUL, sorted case-insensitively by name, and the block is
+    /// terminated by an additional NUL.
+    pub fn to_wide(&self) -> Vec<u16> {
+        let mut vars = self.vars.iter().collect::<Vec<_>>();
+        vars.sort_by_cached_key(|(key, _)| key.to_string_lossy().to_uppercase());
+
+        let mut block = Vec::new();
+        for (key, value) in vars {
+            block.extend(key.encode_wide());
+            block.push(b'=' as u16);
+            block.extend(value.encode_wide());
+            block.push(0);
+        }
+
+        // An empty block still needs two terminating NULs.
+        if block.is_empty() {
+            block.push(0);
+        }
+        block.push(0);
+
+        block
+    }
+
+    fn insert(&mut self, key: OsString, value: OsString) {
+        match self.position(&key) {
+            Some(index) => self.vars[index] = (key, value),
+            None => self.vars.push((key, value)),
+        }
+    }
+
+    fn position(&self, key: &OsStr) -> Option<usize> {
+        self.vars
+            .iter()
+            .position(|(existing, _)| existing.eq_ignore_ascii_case(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wide(entries: &str) -> Vec<u16> {
+        entries.encode_utf16().collect()
+    }
+
+    #[test]
+    fn set_overrides_case_insensitively() {
+        let mut env = EnvironmentBlock::new();
+        env.set("Path", "C:\\a")
+            .unwrap()
+            .set("PATH", "C:\\b")
+            .unwrap();
+
+        assert_eq!(env.len(), 1);
+        assert_eq!(env.get("path"), Some(OsStr::new("C:\\b")));
+        // The name of the latest `set` is kept.
+        assert_eq!(env.iter().next().unwrap().0, "PATH");
+    }
+
+    #[test]
+    fn remove_is_case_insensitive() {
+        let mut env = EnvironmentBlock::new();
+        env.set_all([("KEEP", "1"), ("Drop", "2")]).unwrap();
+        env.remove("DROP").remove("missing");
+
+        assert_eq!(env.len(), 1);
+        assert_eq!(env.get("drop"), None);
+        assert_eq!(env.get("keep"), Some(OsStr::new("1")));
+    }
+
+    #[test]
+    fn to_wide_sorts_and_terminates() {
+        let mut env = EnvironmentBlock::new();
+        env.set_all([("zeta", "3"), ("Alpha", "1"), ("beta", ""), ("=C:", "C:\\")])
+            .unwrap();
+
+        assert_eq!(env.to_wide(), wide("=C:=C:\\\0Alpha=1\0beta=\0zeta=3\0\0"));
+    }
+
+    #[test]
+    fn empty_block_has_two_nuls() {
+        assert_eq!(EnvironmentBlock::new().to_wide(), vec![0, 0]);
+    }
+
+    #[test]
+    fn rejects_invalid_variables() {
+        let mut env = EnvironmentBlock::new();
+
+        assert!(env.set("", "value").is_err());
+        assert!(env.set("A=B", "value").is_err());
+        assert!(env.set("A\0B", "value").is_err());
+        assert!(env.set("KEY", "a\0b").is_err());
+        assert!(env.set_all([("OK", "1"), ("BAD=", "2")]).is_err());
+
+        assert_eq!(env.len(), 1);
+        assert_eq!(env.get("OK"), Some(OsStr::new("1")));
+    }
+
+    #[test]
+    fn inherit_contains_the_current_environment() {
+        let env = EnvironmentBlock::inherit();
+
+        for (key, value) in std::env::vars_os() {
+            assert_eq!(env.get(&key), Some(value.as_os_str()));
+        }
+    }
+}
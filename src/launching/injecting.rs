@@ -1,7 +1,33 @@
-use dll_syringe::process::{BorrowedProcess, OwnedProcess, OwnedProcessModule, Process};
 use dll_syringe::Syringe;
-use eyre::ContextCompat;
+use dll_syringe::process::{
+    BorrowedProcess, BorrowedProcessModule, OwnedProcess, OwnedProcessModule, Process,
+};
+use eyre::{ContextCompat, WrapErr};
 use std::path::Path;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::launching::{LaunchedProcess, waiting};
+use crate::patching::process::BorrowedGameProcess;
+use crate::pe::{Machine, PeErrorKind, PeFile};
+
+/// Errors detected before attempting an injection.
+///
+/// Returned within the [eyre::Report] of the inject functions, use `downcast_ref` to inspect them.
+#[derive(Debug, Error)]
+pub enum InjectErrorKind {
+    #[error("The payload is built for {payload:?}, but the target process runs as {target:?}")]
+    ArchitectureMismatch { payload: Machine, target: Machine },
+
+    #[error("The payload is not a valid DLL: {0}")]
+    InvalidPayload(#[from] PeErrorKind),
+
+    #[error("The payload is an executable, not a DLL")]
+    NotADll,
+
+    #[error("A suspended {target:?} process can't be injected from a {injector:?} injector")]
+    SuspendedArchitectureMismatch { injector: Machine, target: Machine },
+}
 
 /// Inject the given `payload_dll` into a running process.
 ///
@@ -12,6 +38,8 @@ pub fn inject_into_running_process(
     process: BorrowedProcess<'_>,
     payload_dll: &Path,
 ) -> eyre::Result<OwnedProcessModule> {
+    check_payload_architecture(process, payload_dll)?;
+
     let syringe = Syringe::for_process(process.try_to_owned()?);
 
     let injected_module = syringe.find_or_inject(payload_dll)?;
@@ -35,3 +63,197 @@ pub fn inject_into_running_process_by_name(
 
     inject_into_running_process(existing_process.borrowed(), payload_dll)
 }
+
+/// Wait for a process with the given name to start, and inject the given `payload_dll` into it.
+///
+/// If `after_module` is given, injection is delayed until the process has loaded that module (e.g. `d3d11.dll`), as
+/// injecting right after process creation can be too early for payloads which hook into it.
+///
+/// The `timeout` covers both waits, [None] waits indefinitely.
+///
+/// # Returns
+///
+/// The [OwnedProcessModule] of the injected dll if successful.
+pub fn wait_and_inject_by_name(
+    process_name: impl AsRef<str>,
+    after_module: Option<&str>,
+    payload_dll: &Path,
+    timeout: Option<Duration>,
+) -> eyre::Result<OwnedProcessModule> {
+    let start = Instant::now();
+    let process = waiting::wait_for_process_by_name(process_name, timeout)?;
+
+    if let Some(module_name) = after_module {
+        let remaining = timeout.map(|timeout| timeout.saturating_sub(start.elapsed()));
+        waiting::wait_for_module(waiting::as_game_process(&process), module_name, remaining)?;
+    }
+
+    inject_into_running_process(process.borrowed(), payload_dll)
+}
+
+/// Inject the given `payload_dll` into a process launched with [ProcessBuilder::suspended](crate::launching::ProcessBuilder::suspended).
+///
+/// The remote thread running `LoadLibraryW` initialises the loader of the process, so the static imports of the game
+/// (and their `DllMain`s) are loaded before the payload, but the entry point of the game has not run yet.
+///
+/// The module list of a suspended process can't be enumerated, so unlike [inject_into_running_process] this always
+/// injects. For the same reason the injector must run as the same architecture as the game: from an x64 injector
+/// `kernel32.dll` of a WOW64 game can't be located, so this returns
+/// [InjectErrorKind::SuspendedArchitectureMismatch] rather than timing out.
+///
+/// # Returns
+///
+/// The [OwnedProcessModule] of the injected dll if successful.
+pub fn inject_into_suspended_process(
+    launched: &LaunchedProcess,
+    payload_dll: &Path,
+) -> eyre::Result<OwnedProcessModule> {
+    let target = launched.game_process().machine()?;
+    let injector = BorrowedGameProcess::current().machine()?;
+    if injector != target {
+        return Err(InjectErrorKind::SuspendedArchitectureMismatch { injector, target }.into());
+    }
+    check_payload_architecture(launched.process.borrowed(), payload_dll)?;
+
+    let syringe = Syringe::for_process(launched.process.try_clone()?);
+
+    let injected_module = syringe.inject(payload_dll)?;
+
+    Ok(injected_module.try_to_owned()?)
+}
+
+/// Unload a previously injected `module` from the process, e.g. to load a rebuilt payload without restarting the game.
+///
+/// If `cleanup_export` is given and the payload exports a function with that name, it is called first, giving the
+/// payload a chance to remove its hooks and stop its threads. It is declared in the payload as:
+/// ```norun
+/// #[unsafe(no_mangle)]
+/// pub extern "system" fn cleanup() {
+///     // Disable hooks, join threads, ...
+/// }
+/// ```
+/// A payload without such an export is unloaded directly.
+pub fn eject_from_process(
+    process: BorrowedProcess<'_>,
+    module: BorrowedProcessModule<'_>,
+    cleanup_export: Option<&str>,
+) -> eyre::Result<()> {
+    let syringe = Syringe::for_process(process.try_to_owned()?);
+
+    if let Some(export) = cleanup_export {
+        let cleanup = unsafe { syringe.get_raw_procedure::<extern "system" fn()>(module, export) }
+            .wrap_err_with(|| format!("Failed to load cleanup export `{}`", export))?;
+
+        match cleanup {
+            Some(cleanup) => cleanup
+                .call()
+                .wrap_err_with(|| format!("Cleanup export `{}` failed", export))?,
+            None => log::debug!("Payload has no cleanup export `{}`", export),
+        }
+    }
+
+    syringe.eject(module)?;
+
+    Ok(())
+}
+
+/// Unload the payload with the given module name (e.g. `payload.dll`) from the first process with the given name.
+///
+/// See [eject_from_process] for the meaning of `cleanup_export`.
+pub fn eject_from_process_by_name(
+    process_name: impl AsRef<str>,
+    payload_name: impl AsRef<str>,
+    cleanup_export: Option<&str>,
+) -> eyre::Result<()> {
+    let process = OwnedProcess::find_first_by_name(process_name.as_ref())
+        .context("Could not find a process with the given name")?;
+    let module = process
+        .find_module_by_name(payload_name.as_ref())?
+        .with_context(|| format!("The process has no module {}", payload_name.as_ref()))?;
+
+    eject_from_process(process.borrowed(), module.borrowed(), cleanup_export)
+}
+
+/// Check that the `payload_dll` can be loaded by the `process`, see [check_payload_machine].
+pub fn check_payload_architecture(
+    process: BorrowedProcess<'_>,
+    payload_dll: &Path,
+) -> eyre::Result<()> {
+    let payload = std::fs::read(payload_dll)
+        .wrap_err_with(|| format!("Failed to read payload {:?}", payload_dll))?;
+    let target = BorrowedGameProcess::from(process).machine()?;
+
+    check_payload_machine(&payload, target)?;
+
+    Ok(())
+}
+
+/// Check that the `payload` (the contents of a DLL) is built for the `target` architecture.
+pub fn check_payload_machine(payload: &[u8], target: Machine) -> Result<(), InjectErrorKind> {
+    let pe = PeFile::parse(payload)?;
+
+    if !pe.is_dll() {
+        return Err(InjectErrorKind::NotADll);
+    }
+    if pe.machine() != target {
+        return Err(InjectErrorKind::ArchitectureMismatch {
+            payload: pe.machine(),
+            target,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::launching::injecting::{InjectErrorKind, check_payload_machine};
+    use crate::pe::Machine;
+    use crate::pe::testing::TestPe;
+
+    #[test]
+    fn accepts_matching_machine() {
+        for machine in [Machine::X86, Machine::X64, Machine::Arm64] {
+            let payload = TestPe::new(machine).build();
+            assert!(
+                check_payload_machine(&payload, machine).is_ok(),
+                "{machine:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_mismatched_machine() {
+        let payload = TestPe::new(Machine::X86).build();
+        assert!(matches!(
+            check_payload_machine(&payload, Machine::X64),
+            Err(InjectErrorKind::ArchitectureMismatch {
+                payload: Machine::X86,
+                target: Machine::X64,
+            })
+        ));
+
+        let payload = TestPe::new(Machine::X64).build();
+        assert!(matches!(
+            check_payload_machine(&payload, Machine::Arm64),
+            Err(InjectErrorKind::ArchitectureMismatch {
+                payload: Machine::X64,
+                target: Machine::Arm64,
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_executables_and_garbage() {
+        let payload = TestPe::new(Machine::X64).dll(false).build();
+        assert!(matches!(
+            check_payload_machine(&payload, Machine::X64),
+            Err(InjectErrorKind::NotADll)
+        ));
+
+        assert!(matches!(
+            check_payload_machine(b"not a PE file", Machine::X64),
+            Err(InjectErrorKind::InvalidPayload(_))
+        ));
+    }
+}
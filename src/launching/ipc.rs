@@ -0,0 +1,11 @@
+//! The client side of the [ipc](crate::ipc) control channel, for talking to a DLL injected into a launched process.
+//!
+//! # Example
+//! ```norun
+//! let process = launch_process(working_dir, exe_path, std::iter::empty())?;
+//! // ... inject the DLL, which starts an `IpcServer` with the default config.
+//! let endpoint = IpcEndpoint::for_process(process.pid()?.get());
+//! let mut client = IpcClient::connect(&endpoint, Duration::from_secs(10))?;
+//! let response = client.request(&Request::ReloadConfig)?;
+//! ```
+pub use crate::ipc::IpcClient;
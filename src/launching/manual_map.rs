@@ -0,0 +1,624 @@
+//! Manual mapping of a payload DLL, as an alternative to `LoadLibrary` based injection.
+//!
+//! The payload is mapped by hand: its sections are copied into memory allocated in the target, relocations and
+//! imports are applied, and finally its TLS callbacks and `DllMain` are called on a remote thread. It therefore
+//! doesn't appear in the module list of the target.
+//!
+//! The PE handling itself lives in [pe](crate::pe) and works on plain byte buffers, this module only moves the
+//! result into the target process through [BorrowedGameProcess].
+//!
+//! # Limitations
+//! * The payload must be built for the architecture of the target, which must be x86 or x64.
+//! * Implicit TLS (`#[thread_local]`, used by Rust's `thread_local!` on MSVC and thus by practically every Rust
+//!   `cdylib` linking `std`) is not set up. Payloads with a TLS directory describing such data are rejected, and have
+//!   to be loaded with regular injection instead.
+//! * Imports are loaded with `LoadLibraryW` from `kernel32.dll`, which therefore has to be loaded in the target
+//!   already. A freshly launched suspended process hasn't loaded it yet, see the example.
+//! * Functions looking up the module of an address, like `GetModuleHandleExW` with
+//!   `GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS`, don't find the payload.
+//! * Delay-load imports are not resolved ahead of time, and will be resolved by the delay-load helper on first use.
+//!
+//! # Example
+//! ```ignore
+//! let launched = ProcessBuilder::new(exe_path).launch()?;
+//! wait_for_module(launched.game_process(), "kernel32.dll", Some(Duration::from_secs(5)))?;
+//! let mapped = manual_map(launched.game_process(), Path::new("payload.dll"), &Default::default())?;
+//! ```
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use eyre::{ContextCompat, WrapErr};
+use windows::Win32::Foundation::{CloseHandle, WAIT_OBJECT_0};
+use windows::Win32::System::Memory::{
+    MEM_COMMIT, MEM_RELEASE, MEM_RESERVE, PAGE_EXECUTE, PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE,
+    PAGE_NOACCESS, PAGE_PROTECTION_FLAGS, PAGE_READONLY, PAGE_READWRITE, VirtualAllocEx,
+    VirtualFreeEx, VirtualProtectEx,
+};
+use windows::Win32::System::Threading::{CreateRemoteThread, INFINITE, WaitForSingleObject};
+
+use crate::launching::injecting::check_payload_machine;
+use crate::patching::process::BorrowedGameProcess;
+use crate::pe::{
+    DataDirectoryKind, ExportTarget, ImportSymbol, Machine, PeFile, SECTION_EXECUTE, SECTION_READ,
+    SECTION_WRITE, apply_relocations, write_pointer,
+};
+
+/// `DLL_PROCESS_ATTACH`
+const PROCESS_ATTACH: u64 = 1;
+
+#[derive(Debug, Clone)]
+pub struct ManualMapConfig {
+    /// Call the TLS callbacks of the payload before its entry point.
+    pub run_tls_callbacks: bool,
+    /// Call `DllMain` with `DLL_PROCESS_ATTACH`.
+    pub call_entry_point: bool,
+    /// Zero the PE headers once mapping is done, making the payload harder to find by scanning memory.
+    pub erase_headers: bool,
+    /// How long to wait for every remote call (e.g. `DllMain`) to return.
+    pub remote_call_timeout: Duration,
+}
+
+impl Default for ManualMapConfig {
+    fn default() -> Self {
+        Self {
+            run_tls_callbacks: true,
+            call_entry_point: true,
+            erase_headers: false,
+            remote_call_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// A payload mapped into the target by [manual_map].
+#[derive(Debug, Clone, Copy)]
+pub struct MappedModule {
+    /// The address the payload was mapped at, the equivalent of its `HMODULE`.
+    pub base: usize,
+    pub size: usize,
+    /// The absolute address of `DllMain`, if the payload has an entry point.
+    pub entry_point: Option<usize>,
+}
+
+/// Manually map the `payload_dll` into the given `process`.
+///
+/// Dependencies of the payload which aren't loaded in the target yet are loaded with a remote `LoadLibraryW` call.
+pub fn manual_map(
+    process: BorrowedGameProcess<'_>,
+    payload_dll: &Path,
+    config: &ManualMapConfig,
+) -> eyre::Result<MappedModule> {
+    let bytes = std::fs::read(payload_dll)
+        .wrap_err_with(|| format!("Failed to read payload {:?}", payload_dll))?;
+
+    manual_map_bytes(process, &bytes, config)
+}
+
+/// Manually map a payload DLL, given as the contents of its file, into the given `process`.
+///
+/// See [manual_map].
+pub fn manual_map_bytes(
+    process: BorrowedGameProcess<'_>,
+    payload: &[u8],
+    config: &ManualMapConfig,
+) -> eyre::Result<MappedModule> {
+    let pe = PeFile::parse(payload)?;
+
+    check_payload_machine(payload, process.machine()?)?;
+    if !matches!(pe.machine(), Machine::X86 | Machine::X64) {
+        eyre::bail!("Manual mapping is not supported for {:?}", pe.machine());
+    }
+    if pe.implicit_tls_size()? != 0 {
+        eyre::bail!("Payloads using implicit TLS (e.g. `thread_local!`) can't be manually mapped");
+    }
+
+    let size = pe.size_of_image() as usize;
+    let relocations = pe.relocations()?;
+
+    let base = unsafe {
+        let preferred = VirtualAllocEx(
+            process.as_raw_handle(),
+            Some(pe.image_base() as *const c_void),
+            size,
+            MEM_COMMIT | MEM_RESERVE,
+            PAGE_READWRITE,
+        );
+
+        if !preferred.is_null() {
+            preferred
+        } else if !relocations.is_empty() {
+            VirtualAllocEx(
+                process.as_raw_handle(),
+                None,
+                size,
+                MEM_COMMIT | MEM_RESERVE,
+                PAGE_READWRITE,
+            )
+        } else {
+            eyre::bail!(
+                "The payload has no relocations and its preferred base {:#X} is in use",
+                pe.image_base()
+            );
+        }
+    } as usize;
+
+    if base == 0 {
+        return Err(windows::core::Error::from_thread())
+            .wrap_err("Failed to allocate memory for the payload");
+    }
+
+    let mapped = map_at(process, &pe, base, config);
+
+    if mapped.is_err() {
+        let _ = unsafe { VirtualFreeEx(process.as_raw_handle(), base as *mut _, 0, MEM_RELEASE) };
+    }
+
+    mapped
+}
+
+fn map_at(
+    process: BorrowedGameProcess<'_>,
+    pe: &PeFile,
+    base: usize,
+    config: &ManualMapConfig,
+) -> eyre::Result<MappedModule> {
+    let mut image = pe.map_image()?;
+    apply_relocations(
+        &mut image,
+        &pe.relocations()?,
+        (base as u64).wrapping_sub(pe.image_base()),
+    )?;
+
+    let mut resolver = ImportResolver::new(process, pe.is_64_bit(), config.remote_call_timeout)?;
+
+    for import in pe.imports()? {
+        for thunk in &import.thunks {
+            let address = resolver
+                .resolve(&import.dll_name, &thunk.symbol)
+                .wrap_err_with(|| {
+                    format!(
+                        "Failed to resolve {:?} of {}",
+                        thunk.symbol, import.dll_name
+                    )
+                })?;
+            write_pointer(&mut image, thunk.iat_rva, address as u64, pe.is_64_bit())?;
+        }
+    }
+
+    unsafe { process.write_absolute_buffer(base as *mut u8, &image)? };
+    protect_sections(process, pe, base)?;
+
+    let remote = RemoteCaller {
+        process,
+        is_64_bit: pe.is_64_bit(),
+        timeout: config.remote_call_timeout,
+    };
+
+    // Without a registered function table exceptions (and thus panics) can't unwind through the payload.
+    if pe.is_64_bit()
+        && let Some(exceptions) = pe.data_directory(DataDirectoryKind::Exception)
+    {
+        let add_function_table = resolver.resolve(
+            "ntdll.dll",
+            &ImportSymbol::Name {
+                hint: 0,
+                name: "RtlAddFunctionTable".into(),
+            },
+        )?;
+        let added = remote.call(
+            add_function_table,
+            &[
+                (base + exceptions.rva as usize) as u64,
+                (exceptions.size / 12) as u64,
+                base as u64,
+            ],
+        )?;
+        if added as u8 == 0 {
+            log::warn!("RtlAddFunctionTable failed for the mapped payload");
+        }
+    }
+
+    if config.run_tls_callbacks {
+        for callback in pe.tls_callbacks()? {
+            remote.call(base + callback as usize, &[base as u64, PROCESS_ATTACH, 0])?;
+        }
+    }
+
+    let entry_point = (pe.entry_point() != 0).then(|| base + pe.entry_point() as usize);
+
+    if config.call_entry_point
+        && let Some(entry_point) = entry_point
+    {
+        let result = remote.call(entry_point, &[base as u64, PROCESS_ATTACH, 0])?;
+        if result as u32 == 0 {
+            eyre::bail!("DllMain of the payload returned FALSE");
+        }
+    }
+
+    if config.erase_headers {
+        let zeroes = vec![0u8; pe.size_of_headers() as usize];
+        let mut old = PAGE_PROTECTION_FLAGS::default();
+        unsafe {
+            VirtualProtectEx(
+                process.as_raw_handle(),
+                base as *const _,
+                zeroes.len(),
+                PAGE_READWRITE,
+                &mut old,
+            )?;
+            process.write_absolute_buffer(base as *mut u8, &zeroes)?;
+            VirtualProtectEx(
+                process.as_raw_handle(),
+                base as *const _,
+                zeroes.len(),
+                PAGE_NOACCESS,
+                &mut old,
+            )?;
+        }
+    }
+
+    Ok(MappedModule {
+        base,
+        size: image.len(),
+        entry_point,
+    })
+}
+
+/// Apply the final page protections, the headers become read-only.
+fn protect_sections(
+    process: BorrowedGameProcess<'_>,
+    pe: &PeFile,
+    base: usize,
+) -> eyre::Result<()> {
+    let mut old = PAGE_PROTECTION_FLAGS::default();
+
+    unsafe {
+        VirtualProtectEx(
+            process.as_raw_handle(),
+            base as *const _,
+            pe.size_of_headers() as usize,
+            PAGE_READONLY,
+            &mut old,
+        )?;
+    }
+
+    for section in pe.sections() {
+        let size = section.virtual_size.max(section.raw_size) as usize;
+        if size == 0 {
+            continue;
+        }
+
+        let flags = section.characteristics;
+        let protection = match (
+            flags & SECTION_EXECUTE != 0,
+            flags & SECTION_READ != 0,
+            flags & SECTION_WRITE != 0,
+        ) {
+            (true, _, true) => PAGE_EXECUTE_READWRITE,
+            (true, true, false) => PAGE_EXECUTE_READ,
+            (true, false, false) => PAGE_EXECUTE,
+            (false, _, true) => PAGE_READWRITE,
+            (false, true, false) => PAGE_READONLY,
+            (false, false, false) => PAGE_NOACCESS,
+        };
+
+        unsafe {
+            VirtualProtectEx(
+                process.as_raw_handle(),
+                (base + section.virtual_address as usize) as *const _,
+                size,
+                protection,
+                &mut old,
+            )
+            .wrap_err_with(|| format!("Failed to protect section `{}`", section.name))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolves imported symbols to addresses within the target, by parsing the export tables of its modules on disk.
+struct ImportResolver<'a> {
+    process: BorrowedGameProcess<'a>,
+    remote: RemoteCaller<'a>,
+    /// Lower-case module name to base address and path.
+    modules: HashMap<String, (usize, PathBuf)>,
+    /// Parsed export tables by module base.
+    exports: HashMap<usize, Vec<crate::pe::Export>>,
+}
+
+impl<'a> ImportResolver<'a> {
+    /// Forwarders can chain, but never this deep.
+    const MAX_FORWARDS: usize = 16;
+
+    fn new(
+        process: BorrowedGameProcess<'a>,
+        is_64_bit: bool,
+        timeout: Duration,
+    ) -> eyre::Result<Self> {
+        let mut resolver = Self {
+            process,
+            remote: RemoteCaller {
+                process,
+                is_64_bit,
+                timeout,
+            },
+            modules: HashMap::new(),
+            exports: HashMap::new(),
+        };
+        resolver.refresh_modules()?;
+
+        Ok(resolver)
+    }
+
+    fn refresh_modules(&mut self) -> eyre::Result<()> {
+        self.modules = self
+            .process
+            .get_modules()?
+            .into_iter()
+            .map(|module| {
+                (
+                    module.name().to_ascii_lowercase(),
+                    (module.base() as usize, module.module_path()),
+                )
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    fn resolve(&mut self, dll_name: &str, symbol: &ImportSymbol) -> eyre::Result<usize> {
+        let mut dll_name = dll_name.to_owned();
+        let mut symbol = symbol.clone();
+
+        for _ in 0..Self::MAX_FORWARDS {
+            let (base, path) = self.module(&dll_name)?;
+            let exports = self.exports_of(base, &path)?;
+
+            let export = exports
+                .iter()
+                .find(|export| match &symbol {
+                    ImportSymbol::Name { name, .. } => {
+                        export.name.as_deref() == Some(name.as_str())
+                    }
+                    ImportSymbol::Ordinal(ordinal) => export.ordinal == *ordinal,
+                })
+                .with_context(|| format!("{} has no export {:?}", dll_name, symbol))?;
+
+            match &export.target {
+                ExportTarget::Rva(rva) => return Ok(base + *rva as usize),
+                ExportTarget::Forwarder(forwarder) => {
+                    // E.g. `NTDLL.RtlAllocateHeap` or `api-ms-win-core-heap-l1-1-0.HeapAlloc` or `MSVCRT.#12`.
+                    let (module, name) = forwarder
+                        .rsplit_once('.')
+                        .with_context(|| format!("Invalid forwarder: {}", forwarder))?;
+
+                    dll_name = format!("{}.dll", module);
+                    symbol = match name.strip_prefix('#') {
+                        Some(ordinal) => ImportSymbol::Ordinal(ordinal.parse()?),
+                        None => ImportSymbol::Name {
+                            hint: 0,
+                            name: name.into(),
+                        },
+                    };
+                }
+            }
+        }
+
+        eyre::bail!("Too many forwarders resolving {:?}", symbol)
+    }
+
+    /// Find a module in the target, loading it if needed.
+    fn module(&mut self, dll_name: &str) -> eyre::Result<(usize, PathBuf)> {
+        if let Some(module) = self.modules.get(&dll_name.to_ascii_lowercase()) {
+            return Ok(module.clone());
+        }
+        // `LoadLibraryW` is resolved through kernel32 itself, so it can't be loaded this way.
+        if dll_name.eq_ignore_ascii_case("kernel32.dll") {
+            eyre::bail!(
+                "kernel32.dll is not loaded in the target, resume it or use wait_for_module first"
+            );
+        }
+
+        // Also covers API sets like `api-ms-win-crt-runtime-l1-1-0.dll`, which resolve to their host DLL.
+        let load_library = self.resolve(
+            "kernel32.dll",
+            &ImportSymbol::Name {
+                hint: 0,
+                name: "LoadLibraryW".into(),
+            },
+        )?;
+
+        let wide_name = dll_name
+            .encode_utf16()
+            .chain(Some(0))
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        let remote_name = self.remote.alloc(&wide_name, PAGE_READWRITE)?;
+        let loaded = self.remote.call(load_library, &[remote_name as u64]);
+        self.remote.free(remote_name);
+
+        let loaded = loaded? as usize;
+        if loaded == 0 {
+            eyre::bail!("LoadLibraryW failed for {} in the target", dll_name);
+        }
+
+        self.refresh_modules()?;
+        let module = self
+            .modules
+            .values()
+            .find(|(base, _)| *base == loaded)
+            .cloned()
+            .with_context(|| format!("{} was loaded but is not in the module list", dll_name))?;
+        self.modules
+            .insert(dll_name.to_ascii_lowercase(), module.clone());
+
+        Ok(module)
+    }
+
+    fn exports_of(&mut self, base: usize, path: &Path) -> eyre::Result<&Vec<crate::pe::Export>> {
+        match self.exports.entry(base) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                let bytes =
+                    std::fs::read(path).wrap_err_with(|| format!("Failed to read {:?}", path))?;
+                Ok(entry.insert(PeFile::parse(&bytes)?.exports()?))
+            }
+        }
+    }
+}
+
+/// Calls functions in the target on a new remote thread, through a small generated stub.
+#[derive(Clone, Copy)]
+struct RemoteCaller<'a> {
+    process: BorrowedGameProcess<'a>,
+    is_64_bit: bool,
+    timeout: Duration,
+}
+
+impl RemoteCaller<'_> {
+    fn alloc(&self, bytes: &[u8], protection: PAGE_PROTECTION_FLAGS) -> eyre::Result<usize> {
+        let address = unsafe {
+            VirtualAllocEx(
+                self.process.as_raw_handle(),
+                None,
+                bytes.len(),
+                MEM_COMMIT | MEM_RESERVE,
+                protection,
+            )
+        } as usize;
+
+        if address == 0 {
+            return Err(windows::core::Error::from_thread()).wrap_err("VirtualAllocEx failed");
+        }
+
+        if let Err(e) = unsafe {
+            self.process
+                .write_absolute_buffer(address as *mut u8, bytes)
+        } {
+            self.free(address);
+            return Err(e.into());
+        }
+
+        Ok(address)
+    }
+
+    fn free(&self, address: usize) {
+        let _ = unsafe {
+            VirtualFreeEx(
+                self.process.as_raw_handle(),
+                address as *mut _,
+                0,
+                MEM_RELEASE,
+            )
+        };
+    }
+
+    /// Call `function` with up to four pointer-sized `args` (`stdcall` on x86), returning its return value.
+    fn call(&self, function: usize, args: &[u64]) -> eyre::Result<u64> {
+        // Reserve room for the stub, followed by an aligned slot for the return value.
+        let placeholder = if self.is_64_bit {
+            call_stub_x64(function as u64, args, 0)
+        } else {
+            call_stub_x86(function as u32, args, 0)
+        };
+        let result_offset = placeholder.len().next_multiple_of(8);
+        let stub_size = result_offset + 8;
+
+        let address = self.alloc(&vec![0u8; stub_size], PAGE_EXECUTE_READWRITE)?;
+        let result_address = address + result_offset;
+
+        let mut stub = if self.is_64_bit {
+            call_stub_x64(function as u64, args, result_address as u64)
+        } else {
+            call_stub_x86(function as u32, args, result_address as u32)
+        };
+        stub.resize(stub_size, 0);
+
+        unsafe {
+            self.process
+                .write_absolute_buffer(address as *mut u8, &stub)?
+        };
+
+        let thread = unsafe {
+            CreateRemoteThread(
+                self.process.as_raw_handle(),
+                None,
+                0,
+                Some(std::mem::transmute::<
+                    usize,
+                    unsafe extern "system" fn(*mut c_void) -> u32,
+                >(address)),
+                None,
+                0,
+                None,
+            )
+        };
+        let thread = match thread {
+            Ok(thread) => thread,
+            Err(e) => {
+                self.free(address);
+                return Err(e).wrap_err("CreateRemoteThread failed");
+            }
+        };
+
+        let timeout = u32::try_from(self.timeout.as_millis()).unwrap_or(INFINITE);
+        let waited = unsafe { WaitForSingleObject(thread, timeout) };
+        let _ = unsafe { CloseHandle(thread) };
+
+        if waited != WAIT_OBJECT_0 {
+            // The stub might still run, so it is leaked instead of freed.
+            eyre::bail!("Remote call to {:#X} did not finish in time", function);
+        }
+
+        let mut result = [0u8; 8];
+        let read = unsafe {
+            self.process
+                .read_absolute_buffer(result_address as *mut u8, &mut result)
+        };
+        self.free(address);
+        read?;
+
+        Ok(u64::from_le_bytes(result))
+    }
+}
+
+/// `sub rsp, 0x28; mov rcx/rdx/r8/r9, args; mov rax, function; call rax; mov [result], rax; add rsp, 0x28; ret`
+fn call_stub_x64(function: u64, args: &[u64], result: u64) -> Vec<u8> {
+    const ARG_REGISTERS: [[u8; 2]; 4] = [[0x48, 0xB9], [0x48, 0xBA], [0x49, 0xB8], [0x49, 0xB9]];
+    assert!(
+        args.len() <= ARG_REGISTERS.len(),
+        "At most four arguments are supported"
+    );
+
+    let mut stub = vec![0x48, 0x83, 0xEC, 0x28];
+    for (register, arg) in ARG_REGISTERS.iter().zip(args) {
+        stub.extend_from_slice(register);
+        stub.extend_from_slice(&arg.to_le_bytes());
+    }
+    stub.extend_from_slice(&[0x48, 0xB8]);
+    stub.extend_from_slice(&function.to_le_bytes());
+    stub.extend_from_slice(&[0xFF, 0xD0]);
+    stub.extend_from_slice(&[0x48, 0xA3]);
+    stub.extend_from_slice(&result.to_le_bytes());
+    stub.extend_from_slice(&[0x48, 0x83, 0xC4, 0x28, 0xC3]);
+    stub
+}
+
+/// `push args (reversed); mov eax, function; call eax; mov [result], eax; ret 4`
+fn call_stub_x86(function: u32, args: &[u64], result: u32) -> Vec<u8> {
+    let mut stub = Vec::new();
+    for arg in args.iter().rev() {
+        stub.push(0x68);
+        stub.extend_from_slice(&(*arg as u32).to_le_bytes());
+    }
+    stub.push(0xB8);
+    stub.extend_from_slice(&function.to_le_bytes());
+    stub.extend_from_slice(&[0xFF, 0xD0]);
+    stub.push(0xA3);
+    stub.extend_from_slice(&result.to_le_bytes());
+    stub.extend_from_slice(&[0xC2, 0x04, 0x00]);
+    stub
+}
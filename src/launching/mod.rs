@@ -1,16 +1,29 @@
-use std::os::windows::io::FromRawHandle;
 use std::path::Path;
 
-use dll_syringe::process::OwnedProcess;
-use eyre::ContextCompat;
-use windows::core::HSTRING;
+use dll_syringe::process::{OwnedProcess, OwnedProcessModule};
 pub use windows::Win32::System::Threading;
-use windows::Win32::System::Threading::{PROCESS_INFORMATION, STARTUPINFOW};
 
+pub use builder::*;
+pub use children::*;
+pub use environment::*;
+pub use remote_call::*;
+pub use waiting::*;
+
+mod builder;
+mod children;
+mod environment;
 pub mod injecting;
+#[cfg(feature = "ipc")]
+pub mod ipc;
+#[cfg(feature = "manual-map")]
+pub mod manual_map;
+mod remote_call;
+mod waiting;
 
 /// Launch the given executable within the provided `working_dir`.
 ///
+/// Shorthand for [ProcessBuilder], which also allows passing arguments or redirecting the standard streams.
+///
 /// # Returns
 ///
 /// The owned process handle, which has full privileges within the spawned process' memory space.
@@ -19,34 +32,47 @@ pub fn launch_process(
     exe_path: &Path,
     env: impl Iterator<Item = (String, String)>,
 ) -> eyre::Result<OwnedProcess> {
-    let env = std::env::vars()
-        .chain(env)
-        .fold(String::new(), |acc, (k, v)| format!("{}{}={}", acc, k, v))
-        .encode_utf16()
-        .chain(Some(0))
-        .collect::<Vec<u16>>();
+    Ok(ProcessBuilder::new(exe_path)
+        .current_dir(working_dir)
+        .envs(env)
+        .launch()?
+        .into_process())
+}
 
-    let startup_info = STARTUPINFOW::default();
-    let mut process_info = PROCESS_INFORMATION::default();
-    let exe_path = HSTRING::from(exe_path);
-    let working_dir = HSTRING::from(working_dir);
+/// Launch the given executable within the provided `working_dir`, with its main thread suspended.
+///
+/// Unlike [launch_process] this allows to prepare the process, e.g. by injecting a DLL, before the game starts
+/// initialising. Resume it with [LaunchedProcess::resume].
+pub fn launch_suspended(
+    working_dir: &Path,
+    exe_path: &Path,
+    env: impl Iterator<Item = (String, String)>,
+) -> eyre::Result<LaunchedProcess> {
+    ProcessBuilder::new(exe_path)
+        .current_dir(working_dir)
+        .envs(env)
+        .suspended(true)
+        .launch()
+}
 
-    unsafe {
-        Threading::CreateProcessW(
-            &exe_path,
-            None,
-            None,
-            None,
-            false,
-            Threading::CREATE_UNICODE_ENVIRONMENT,
-            Some(env.as_ptr() as *const _),
-            &working_dir,
-            &startup_info,
-            &mut process_info,
-        )
-        .ok()
-        .context("Failed to create process")?;
+/// Launch the given executable suspended, inject the `payload_dll`, and only then resume the main thread.
+///
+/// The `DllMain` of the payload has therefore run before the entry point of the game. If injecting fails the process
+/// is terminated.
+///
+/// # Returns
+///
+/// The owned process handle and the [OwnedProcessModule] of the injected dll.
+pub fn launch_and_inject(
+    working_dir: &Path,
+    exe_path: &Path,
+    env: impl Iterator<Item = (String, String)>,
+    payload_dll: &Path,
+) -> eyre::Result<(OwnedProcess, OwnedProcessModule)> {
+    let (launched, injected) = ProcessBuilder::new(exe_path)
+        .current_dir(working_dir)
+        .envs(env)
+        .launch_and_inject(payload_dll)?;
 
-        Ok(OwnedProcess::from_raw_handle(process_info.hProcess.0 as _))
-    }
+    Ok((launched.into_process(), injected))
 }
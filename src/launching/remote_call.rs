@@ -0,0 +1,93 @@
+//! Calling exported functions of an injected payload, with [serde] (de)serialised arguments and results.
+//!
+//! The payload declares its exports with the `payload_procedure!` macro of `dll_syringe`, which requires a direct
+//! dependency on `dll-syringe` with the `payload-utils` feature in the payload crate:
+//! ```norun
+//! dll_syringe::payload_procedure! {
+//!     fn configure(config: ModConfig) -> bool {
+//!         apply_config(config)
+//!     }
+//! }
+//! ```
+//!
+//! The launcher then calls it right after injection:
+//! ```norun
+//! let (launched, module) = launch_and_inject(working_dir, exe_path, std::iter::empty(), payload_dll)?;
+//! let payload = RemotePayload::new(module)?;
+//! let applied: bool = unsafe { payload.call("configure", &config)? };
+//! ```
+use dll_syringe::Syringe;
+use dll_syringe::process::OwnedProcessModule;
+use eyre::{ContextCompat, WrapErr};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// An injected payload whose exports can be called from the injecting process.
+#[derive(Debug)]
+pub struct RemotePayload {
+    syringe: Syringe,
+    module: OwnedProcessModule,
+}
+
+impl RemotePayload {
+    /// Wrap a module returned by e.g. [inject_into_running_process](crate::launching::injecting::inject_into_running_process).
+    pub fn new(module: OwnedProcessModule) -> eyre::Result<Self> {
+        let syringe = Syringe::for_process(module.process().try_clone()?);
+
+        Ok(Self { syringe, module })
+    }
+
+    pub fn module(&self) -> &OwnedProcessModule {
+        &self.module
+    }
+
+    /// Call the export `name` of the payload with the given `arg`, and wait for its result.
+    ///
+    /// A panic within the export is caught by the payload and returned as an `Err`.
+    ///
+    /// # Safety
+    ///
+    /// The export must be declared with `payload_procedure!`, taking an `A` and returning an `R`.
+    pub unsafe fn call<A, R>(&self, name: &str, arg: &A) -> eyre::Result<R>
+    where
+        A: Serialize + 'static,
+        R: DeserializeOwned + 'static,
+    {
+        let procedure = unsafe {
+            self.syringe
+                .get_payload_procedure::<fn(A) -> R>(self.module.borrowed(), name)
+        }
+        .wrap_err_with(|| format!("Failed to load payload export `{}`", name))?
+        .with_context(|| format!("The payload has no export `{}`", name))?;
+
+        procedure
+            .call(arg)
+            .wrap_err_with(|| format!("Remote call of payload export `{}` failed", name))
+    }
+
+    /// Call the export `name` of the payload, which takes no arguments, and wait for its result.
+    ///
+    /// # Safety
+    ///
+    /// The export must be declared with `payload_procedure!`, taking no arguments and returning an `R`.
+    pub unsafe fn call_no_args<R>(&self, name: &str) -> eyre::Result<R>
+    where
+        R: DeserializeOwned + 'static,
+    {
+        let procedure = unsafe {
+            self.syringe
+                .get_payload_procedure::<fn() -> R>(self.module.borrowed(), name)
+        }
+        .wrap_err_with(|| format!("Failed to load payload export `{}`", name))?
+        .with_context(|| format!("The payload has no export `{}`", name))?;
+
+        procedure
+            .call()
+            .wrap_err_with(|| format!("Remote call of payload export `{}` failed", name))
+    }
+
+    /// Release the payload, it stays loaded in the target.
+    pub fn into_module(self) -> OwnedProcessModule {
+        self.module
+    }
+}
@@ -0,0 +1,109 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use dll_syringe::process::{OwnedProcess, Process};
+
+use crate::patching::process::{BorrowedGameProcess, Module};
+
+/// How often the process list, or the module list of a process, is checked while waiting.
+pub const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Wait until a process with the given executable name (e.g. `game.exe`, compared case-insensitively) is running.
+///
+/// Will keep checking every [WAIT_POLL_INTERVAL] until either the `timeout` has been reached, or a process has been
+/// found. A `timeout` of [None] waits indefinitely.
+pub fn wait_for_process_by_name(
+    process_name: impl AsRef<str>,
+    timeout: Option<Duration>,
+) -> eyre::Result<OwnedProcess> {
+    let process_name = process_name.as_ref();
+
+    poll_until(timeout, || {
+        OwnedProcess::all().into_iter().find(|process| {
+            process
+                .base_name()
+                .is_ok_and(|name| name.eq_ignore_ascii_case(process_name))
+        })
+    })
+    .ok_or_else(|| eyre::eyre!("Timed out waiting for process {}", process_name))
+}
+
+/// Wait until a process running the executable at `exe_path` is running.
+///
+/// Unlike [wait_for_process_by_name] this distinguishes between multiple installations of the same game.
+///
+/// See [wait_for_process_by_name] for the meaning of `timeout`.
+pub fn wait_for_process_by_path(
+    exe_path: &Path,
+    timeout: Option<Duration>,
+) -> eyre::Result<OwnedProcess> {
+    let exe_path = canonical_path(exe_path);
+    let exe_name = exe_path
+        .file_name()
+        .ok_or_else(|| eyre::eyre!("Not an executable path: {:?}", exe_path))?;
+
+    poll_until(timeout, || {
+        OwnedProcess::all().into_iter().find(|process| {
+            // Only canonicalize the paths of processes which could match at all.
+            process.path().is_ok_and(|path| {
+                path.file_name()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(exe_name))
+                    && canonical_path(&path) == exe_path
+            })
+        })
+    })
+    .ok_or_else(|| eyre::eyre!("Timed out waiting for process {:?}", exe_path))
+}
+
+/// Wait until the module with the given name (e.g. `d3d11.dll`, compared case-insensitively) has been loaded by the
+/// `process`.
+///
+/// The module list of a process which is still initialising can fail to be enumerated, such failures are retried as
+/// well. See [wait_for_process_by_name] for the meaning of `timeout`.
+pub fn wait_for_module(
+    process: BorrowedGameProcess<'_>,
+    module_name: impl AsRef<str>,
+    timeout: Option<Duration>,
+) -> eyre::Result<Module> {
+    let module_name = module_name.as_ref();
+
+    poll_until(timeout, || {
+        process
+            .get_modules()
+            .ok()?
+            .into_iter()
+            .find(|module| module.name().eq_ignore_ascii_case(module_name))
+    })
+    .ok_or_else(|| {
+        eyre::eyre!(
+            "Timed out waiting for module {} in process {}",
+            module_name,
+            process.pid()
+        )
+    })
+}
+
+/// The [GameProcess] view of a process found by the functions above.
+pub fn as_game_process(process: &OwnedProcess) -> BorrowedGameProcess<'_> {
+    process.into()
+}
+
+fn poll_until<T>(timeout: Option<Duration>, mut poll: impl FnMut() -> Option<T>) -> Option<T> {
+    let start = Instant::now();
+    let timeout = timeout.unwrap_or(Duration::MAX);
+
+    loop {
+        if let Some(found) = poll() {
+            break Some(found);
+        }
+
+        if start.elapsed() > timeout {
+            break None;
+        }
+        std::thread::sleep(WAIT_POLL_INTERVAL);
+    }
+}
+
+fn canonical_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -1,37 +1,62 @@
 //! This library contains utilities and re-exports the dependencies.
 #![allow(unsafe_op_in_unsafe_fn)]
 
+#[cfg(windows)]
 use std::ffi::OsString;
+#[cfg(windows)]
 use std::os::windows::ffi::OsStringExt;
+#[cfg(windows)]
 use std::path::PathBuf;
 
-#[cfg(feature = "launching")]
+#[cfg(all(windows, feature = "launching"))]
 pub use dll_syringe;
 
 pub use patternscan;
+#[cfg(windows)]
 pub use retour;
+#[cfg(windows)]
 use windows::Win32::Foundation::HMODULE;
+#[cfg(windows)]
 use windows::Win32::System::LibraryLoader::{
-    GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT, GetModuleFileNameW, GetModuleHandleExW,
+    GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS, GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+    GetModuleFileNameW, GetModuleHandleExW,
 };
 
+#[cfg(windows)]
 pub mod proxying;
 
-#[cfg(feature = "launching")]
+#[cfg(all(windows, feature = "launching"))]
 pub mod launching;
 
-#[cfg(feature = "patching")]
+#[cfg(all(windows, feature = "patching"))]
 pub mod patching;
 
+#[cfg(windows)]
 pub mod raw_input;
 
+#[cfg(windows)]
 pub mod console;
+#[cfg(all(windows, feature = "crash-dump"))]
+pub mod crash_dump;
+#[cfg(feature = "debug-console")]
+pub mod debug_console;
+#[cfg(feature = "ipc")]
+pub mod ipc;
+#[cfg(all(windows, feature = "logging"))]
+pub mod logging;
+#[cfg(windows)]
 pub mod pausing;
+#[cfg(windows)]
+pub mod reporting;
+
+pub mod pe;
 pub mod pointer;
+pub mod proxy_gen;
 
 /// Retrieves the system directory of the current user.
 ///
 /// Here 'pristine' `DLL`s can be found and loaded for proxying.
+#[cfg(windows)]
 pub fn get_system_directory() -> eyre::Result<PathBuf> {
     let mut buffer = [0; 512];
     // SAFETY: If the buffer is too small the written bytes will be larger than `buffer.len()`, and we will return an Err.
@@ -52,7 +77,31 @@ pub fn get_system_directory() -> eyre::Result<PathBuf> {
     }
 }
 
+/// Retrieves the `SysWOW64` directory, which contains the 32-bit system `DLL`s on 64-bit Windows.
+///
+/// Fails on 32-bit Windows, as there is no such directory.
+#[cfg(windows)]
+pub fn get_system_wow64_directory() -> eyre::Result<PathBuf> {
+    let mut buffer = [0; 512];
+    // SAFETY: Same as `get_system_directory`.
+    let written_bytes = unsafe {
+        windows::Win32::System::SystemInformation::GetSystemWow64DirectoryW(Some(&mut buffer))
+    };
+
+    if written_bytes == 0 || written_bytes > buffer.len() as u32 {
+        Err(eyre::eyre!(
+            "Failed to get SysWOW64 directory, written_bytes: {}",
+            written_bytes
+        ))
+    } else {
+        Ok(PathBuf::from(OsString::from_wide(
+            &buffer[..written_bytes as usize],
+        )))
+    }
+}
+
 /// Retrieves the path to the given DLL module.
+#[cfg(windows)]
 pub fn get_current_dll_path(
     hinst_dll: windows::Win32::Foundation::HMODULE,
 ) -> eyre::Result<PathBuf> {
@@ -65,14 +114,16 @@ pub fn get_current_dll_path(
     }
 }
 
-/// Retrieves the current module, if it exists.
+/// Retrieves the module containing this library, i.e. the DLL it was compiled into.
+#[cfg(windows)]
 pub fn get_current_module() -> eyre::Result<HMODULE> {
     let mut result = HMODULE::default();
 
     unsafe {
+        // With `FROM_ADDRESS` the 'name' is any address within the module, so we pass this very function.
         GetModuleHandleExW(
-            GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
-            windows::core::w!("get_current_module"),
+            GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+            windows::core::PCWSTR(get_current_module as *const u16),
             &mut result,
         )?
     }
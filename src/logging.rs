@@ -0,0 +1,409 @@
+//! A [log] backend for injected DLLs, writing to the console and a rotating log file.
+//!
+//! Console output is only written once a console was allocated with [alloc_console](crate::console::alloc_console),
+//! and is colored if [enable_console_colors](crate::console::enable_console_colors) was called.
+//!
+//! The level is determined by, in order:
+//! 1. The environment variable [LoggerConfig::env_var], e.g. `RUST_LOG=debug`.
+//! 2. The first non-empty, non-comment line of [LoggerConfig::level_file].
+//! 3. [LoggerConfig::level].
+//!
+//! # Example
+//! ```norun
+//! fn attach(hinst_dll: windows::Win32::Foundation::HMODULE) -> eyre::Result<()> {
+//!     rust_hooking_utils::console::alloc_console()?;
+//!     rust_hooking_utils::console::enable_console_colors();
+//!     rust_hooking_utils::logging::init_logger(Default::default())?;
+//!     log::info!("Hello World!");
+//!     Ok(())
+//! }
+//! ```
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::console::{are_console_colors_enabled, is_console_allocated};
+use crate::reporting::local_timestamp;
+
+#[derive(Debug, Clone)]
+pub struct LoggerConfig {
+    /// The level used if neither the environment variable nor the level file specify one.
+    pub level: LevelFilter,
+    /// The environment variable to read the level from, [None] to ignore the environment.
+    pub env_var: Option<String>,
+    /// A file containing the level (e.g. `debug`), relative paths are resolved against the folder of our own DLL.
+    pub level_file: Option<PathBuf>,
+    /// Write to the allocated console, if there is one.
+    pub console: bool,
+    /// The log file, relative paths are resolved against the folder of our own DLL. [None] disables the log file.
+    pub file: Option<PathBuf>,
+    /// Once the log file grows beyond this many bytes it is rotated.
+    pub max_file_size: u64,
+    /// How many rotated files (`<file>.1`, `<file>.2`, ...) are kept besides the current one.
+    pub max_rotated_files: usize,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        Self {
+            level: LevelFilter::Info,
+            env_var: Some("RUST_LOG".into()),
+            level_file: Some("log_level.txt".into()),
+            console: true,
+            file: Some("logs/output.log".into()),
+            max_file_size: 5 * 1024 * 1024,
+            max_rotated_files: 3,
+        }
+    }
+}
+
+/// Install the logger as the global [log] backend.
+///
+/// Fails if a logger was already installed.
+pub fn init_logger(config: LoggerConfig) -> eyre::Result<()> {
+    let dll_path = crate::get_current_dll_path(crate::get_current_module()?)?;
+    let dll_dir = dll_path
+        .parent()
+        .ok_or_else(|| eyre::eyre!("Own DLL path has no parent: {:?}", dll_path))?;
+
+    let level = resolve_level(&config, dll_dir);
+
+    let file = match config.file {
+        Some(path) => {
+            let path = dll_dir.join(path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            Some(RotatingFile::open(
+                path,
+                config.max_file_size,
+                config.max_rotated_files,
+            )?)
+        }
+        None => None,
+    };
+
+    let logger = Logger {
+        level,
+        console: config.console,
+        file: Mutex::new(file),
+    };
+
+    log::set_logger(Box::leak(Box::new(logger)))
+        .map_err(|e| eyre::eyre!("Failed to install the logger: {}", e))?;
+    log::set_max_level(level);
+
+    Ok(())
+}
+
+fn resolve_level(config: &LoggerConfig, dll_dir: &Path) -> LevelFilter {
+    let from_env = config
+        .env_var
+        .as_ref()
+        .and_then(|var| std::env::var(var).ok())
+        .and_then(|value| LevelFilter::from_str(value.trim()).ok());
+
+    let from_file = || {
+        let contents = std::fs::read_to_string(dll_dir.join(config.level_file.as_ref()?)).ok()?;
+        let line = contents
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))?;
+
+        LevelFilter::from_str(line).ok()
+    };
+
+    from_env.or_else(from_file).unwrap_or(config.level)
+}
+
+struct Logger {
+    level: LevelFilter,
+    console: bool,
+    file: Mutex<Option<RotatingFile>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = local_timestamp();
+        let level = record.level();
+        let target = record.target();
+
+        if self.console && is_console_allocated() {
+            let line = if are_console_colors_enabled() {
+                format!(
+                    "\x1b[90m{}\x1b[0m {}{:<5}\x1b[0m \x1b[90m{}\x1b[0m {}",
+                    timestamp,
+                    level_color(level),
+                    level,
+                    target,
+                    record.args()
+                )
+            } else {
+                format!("{} {:<5} {} {}", timestamp, level, target, record.args())
+            };
+
+            let _ = writeln!(std::io::stderr(), "{}", line);
+        }
+
+        if let Some(file) = self.file.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            let line = format!("{} {:<5} {} {}\n", timestamp, level, target, record.args());
+            if let Err(e) = file.write(line.as_bytes()) {
+                eprintln!("Failed to write to the log file: {}", e);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = self.file.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            let _ = file.file.flush();
+        }
+    }
+}
+
+fn level_color(level: Level) -> &'static str {
+    match level {
+        Level::Error => "\x1b[31m",
+        Level::Warn => "\x1b[33m",
+        Level::Info => "\x1b[32m",
+        Level::Debug => "\x1b[34m",
+        Level::Trace => "\x1b[35m",
+    }
+}
+
+/// A log file which is moved to `<path>.1` (shifting older files up) once it exceeds its maximum size.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_rotated: usize,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_size: u64, max_rotated: usize) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_size,
+            max_rotated,
+        })
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        if self.size > 0 && self.size + bytes.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        self.file.write_all(bytes)?;
+        self.size += bytes.len() as u64;
+
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let rotated = |index: usize| {
+            let mut path = self.path.clone().into_os_string();
+            path.push(format!(".{}", index));
+            PathBuf::from(path)
+        };
+
+        if self.max_rotated == 0 {
+            self.file.set_len(0)?;
+        } else {
+            for index in (1..self.max_rotated).rev() {
+                let from = rotated(index);
+                if from.exists() {
+                    std::fs::rename(&from, rotated(index + 1))?;
+                }
+            }
+
+            std::fs::rename(&self.path, rotated(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh folder in the temp directory, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "rust_hooking_utils_logging_{}_{}",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn read(&self, name: &str) -> Option<String> {
+            std::fs::read_to_string(self.0.join(name)).ok()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn level_config(level_file: &str) -> LoggerConfig {
+        LoggerConfig {
+            level: LevelFilter::Warn,
+            env_var: None,
+            level_file: Some(level_file.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rotates_once_the_size_is_exceeded() {
+        let dir = TempDir::new("rotate_size");
+        let mut file = RotatingFile::open(dir.0.join("out.log"), 10, 3).unwrap();
+
+        // Exactly reaching the maximum doesn't rotate yet.
+        file.write(b"0123456789").unwrap();
+        assert_eq!(dir.read("out.log.1"), None);
+
+        file.write(b"a").unwrap();
+        assert_eq!(dir.read("out.log.1").as_deref(), Some("0123456789"));
+        assert_eq!(dir.read("out.log").as_deref(), Some("a"));
+
+        // An oversized line still ends up in a file of its own, rather than rotating an empty file.
+        let dir = TempDir::new("rotate_oversized");
+        let mut file = RotatingFile::open(dir.0.join("out.log"), 4, 3).unwrap();
+        file.write(b"too long").unwrap();
+        assert_eq!(dir.read("out.log").as_deref(), Some("too long"));
+        assert_eq!(dir.read("out.log.1"), None);
+    }
+
+    #[test]
+    fn keeps_at_most_the_configured_rotated_files() {
+        let dir = TempDir::new("rotate_count");
+        let mut file = RotatingFile::open(dir.0.join("out.log"), 1, 2).unwrap();
+
+        for line in ["1", "2", "3", "4"] {
+            file.write(line.as_bytes()).unwrap();
+        }
+
+        assert_eq!(dir.read("out.log").as_deref(), Some("4"));
+        assert_eq!(dir.read("out.log.1").as_deref(), Some("3"));
+        assert_eq!(dir.read("out.log.2").as_deref(), Some("2"));
+        assert_eq!(dir.read("out.log.3"), None);
+    }
+
+    #[test]
+    fn truncates_without_rotated_files() {
+        let dir = TempDir::new("rotate_none");
+        std::fs::write(dir.0.join("out.log"), "previous run").unwrap();
+        let mut file = RotatingFile::open(dir.0.join("out.log"), 12, 0).unwrap();
+
+        file.write(b"new").unwrap();
+
+        assert_eq!(dir.read("out.log").as_deref(), Some("new"));
+        assert_eq!(dir.read("out.log.1"), None);
+    }
+
+    #[test]
+    fn parses_every_level_from_the_level_file() {
+        let dir = TempDir::new("levels");
+
+        for (contents, level) in [
+            ("off", LevelFilter::Off),
+            ("error", LevelFilter::Error),
+            ("warn", LevelFilter::Warn),
+            ("info", LevelFilter::Info),
+            ("debug", LevelFilter::Debug),
+            ("trace", LevelFilter::Trace),
+            ("DEBUG", LevelFilter::Debug),
+            (
+                "# The level of the mod\n\n  trace  \r\n",
+                LevelFilter::Trace,
+            ),
+        ] {
+            std::fs::write(dir.0.join("level.txt"), contents).unwrap();
+            assert_eq!(
+                resolve_level(&level_config("level.txt"), &dir.0),
+                level,
+                "{contents:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn falls_back_on_invalid_or_missing_levels() {
+        let dir = TempDir::new("invalid_levels");
+
+        for contents in ["verbose", "", "# only a comment", "info debug"] {
+            std::fs::write(dir.0.join("level.txt"), contents).unwrap();
+            assert_eq!(
+                resolve_level(&level_config("level.txt"), &dir.0),
+                LevelFilter::Warn,
+                "{contents:?}"
+            );
+        }
+
+        assert_eq!(
+            resolve_level(&level_config("missing.txt"), &dir.0),
+            LevelFilter::Warn
+        );
+    }
+
+    #[test]
+    fn environment_takes_precedence_over_the_level_file() {
+        let dir = TempDir::new("env_level");
+        std::fs::write(dir.0.join("level.txt"), "info").unwrap();
+
+        // Unique names, as tests run in parallel within the same environment.
+        let config = |env_var: &str| LoggerConfig {
+            env_var: Some(env_var.into()),
+            ..level_config("level.txt")
+        };
+        unsafe {
+            std::env::set_var("RUST_HOOKING_UTILS_TEST_LEVEL", " Error ");
+            std::env::set_var("RUST_HOOKING_UTILS_TEST_INVALID_LEVEL", "loud");
+        }
+
+        assert_eq!(
+            resolve_level(&config("RUST_HOOKING_UTILS_TEST_LEVEL"), &dir.0),
+            LevelFilter::Error
+        );
+        assert_eq!(
+            resolve_level(&config("RUST_HOOKING_UTILS_TEST_INVALID_LEVEL"), &dir.0),
+            LevelFilter::Info
+        );
+        assert_eq!(
+            resolve_level(&config("RUST_HOOKING_UTILS_TEST_UNSET_LEVEL"), &dir.0),
+            LevelFilter::Info
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! Listing the running processes, to find the game to inject into or patch.
+//!
+//! # Example
+//! ```norun
+//! let processes = find_processes(
+//!     |info| info.name.eq_ignore_ascii_case("game.exe") && info.machine == Some(Machine::X64),
+//!     PROCESS_ALL_ACCESS,
+//! )?;
+//! ```
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::os::windows::ffi::OsStringExt;
+use std::path::PathBuf;
+
+use windows::Win32::Foundation::{CloseHandle, HWND, LPARAM};
+use windows::Win32::System::Console::GetConsoleWindow;
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW, TH32CS_SNAPPROCESS,
+};
+use windows::Win32::System::Threading::{PROCESS_ACCESS_RIGHTS, PROCESS_QUERY_LIMITED_INFORMATION};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GW_OWNER, GetWindow, GetWindowThreadProcessId, IsWindowVisible,
+};
+use windows::core::BOOL;
+
+use crate::patching::process::{OwnedGameProcess, ProcessErrorKind, Result, Window};
+use crate::pe::Machine;
+
+/// A snapshot of the information about a running process.
+///
+/// Details which require access to the process (e.g. of elevated or protected processes) are [None] if that access is
+/// denied.
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub parent_pid: u32,
+    /// The file name of the executable, e.g. `game.exe`.
+    pub name: String,
+    pub image_path: Option<PathBuf>,
+    pub machine: Option<Machine>,
+    /// The title of the main window, see [get_main_window](crate::patching::process::GameProcess::get_main_window).
+    pub main_window_title: Option<String>,
+}
+
+impl ProcessInfo {
+    pub fn is_64_bit(&self) -> Option<bool> {
+        self.machine
+            .map(|machine| matches!(machine, Machine::X64 | Machine::Arm64))
+    }
+
+    /// Open a handle to this process with the given `access` rights, e.g. `PROCESS_ALL_ACCESS`.
+    pub fn open(&self, access: PROCESS_ACCESS_RIGHTS) -> Result<OwnedGameProcess> {
+        OwnedGameProcess::open(self.pid, access)
+    }
+}
+
+/// List all running processes.
+pub fn list_processes() -> Result<Vec<ProcessInfo>> {
+    let mut main_windows = main_windows();
+
+    Ok(process_snapshot()?
+        .iter()
+        .map(|entry| {
+            let (image_path, machine) = match OwnedGameProcess::open(
+                entry.th32ProcessID,
+                PROCESS_QUERY_LIMITED_INFORMATION,
+            ) {
+                Ok(process) => {
+                    let process = process.borrowed();
+                    (process.image_path().ok(), process.machine().ok())
+                }
+                Err(_) => (None, None),
+            };
+
+            let main_window_title = main_windows
+                .remove(&entry.th32ProcessID)
+                .map(|window| window.title());
+
+            ProcessInfo {
+                pid: entry.th32ProcessID,
+                parent_pid: entry.th32ParentProcessID,
+                name: wide_to_string(&entry.szExeFile),
+                image_path,
+                machine,
+                main_window_title,
+            }
+        })
+        .collect())
+}
+
+/// Open all running processes matching the `predicate` with the given `access` rights.
+///
+/// Processes which match but can't be opened with the requested rights are skipped.
+pub fn find_processes(
+    mut predicate: impl FnMut(&ProcessInfo) -> bool,
+    access: PROCESS_ACCESS_RIGHTS,
+) -> Result<Vec<OwnedGameProcess>> {
+    Ok(list_processes()?
+        .iter()
+        .filter(|info| predicate(info))
+        .filter_map(|info| match info.open(access) {
+            Ok(process) => Some(process),
+            Err(e) => {
+                log::debug!("Failed to open process {} ({}): {}", info.name, info.pid, e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// Open the first running process matching the `predicate`, see [find_processes].
+pub fn find_process(
+    mut predicate: impl FnMut(&ProcessInfo) -> bool,
+    access: PROCESS_ACCESS_RIGHTS,
+) -> Result<Option<OwnedGameProcess>> {
+    Ok(list_processes()?
+        .iter()
+        .filter(|info| predicate(info))
+        .find_map(|info| info.open(access).ok()))
+}
+
+/// The main window of every process which has one, see
+/// [get_main_window](crate::patching::process::GameProcess::get_main_window).
+///
+/// Collected in a single pass over all windows, rather than one pass per process.
+fn main_windows() -> HashMap<u32, Window> {
+    struct HandleData {
+        console_window: HWND,
+        windows: HashMap<u32, Window>,
+    }
+
+    unsafe extern "system" fn enum_callback(handle: HWND, param: LPARAM) -> BOOL {
+        let data = unsafe { &mut *(param.0 as *mut HandleData) };
+        let mut proc_id = 0;
+        unsafe { GetWindowThreadProcessId(handle, Some(&mut proc_id)) };
+
+        // The same criteria as `get_main_window`: a visible window without an owner, which isn't our own console.
+        let is_main_window = handle != data.console_window
+            && unsafe { GetWindow(handle, GW_OWNER) }.is_err()
+            && unsafe { IsWindowVisible(handle) }.as_bool();
+        if is_main_window {
+            data.windows.entry(proc_id).or_insert(Window(handle));
+        }
+
+        true.into()
+    }
+
+    let mut data = HandleData {
+        console_window: unsafe { GetConsoleWindow() },
+        windows: HashMap::new(),
+    };
+
+    unsafe {
+        // Without windows (e.g. in a service) this fails, which just means no process has a main window.
+        let _ = EnumWindows(
+            Some(enum_callback),
+            LPARAM((&mut data as *mut HandleData) as isize),
+        );
+    }
+
+    data.windows
+}
+
+/// The raw `Toolhelp32` entries of all running processes.
+pub(crate) fn process_snapshot() -> Result<Vec<PROCESSENTRY32W>> {
+    let snapshot = unsafe {
+        CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)
+            .map_err(|_| ProcessErrorKind::InvalidHandleValue)?
+    };
+
+    let mut entry = PROCESSENTRY32W {
+        dwSize: size_of::<PROCESSENTRY32W>() as u32,
+        ..Default::default()
+    };
+    let mut entries = Vec::new();
+
+    let mut next = unsafe { Process32FirstW(snapshot, &mut entry) };
+    while next.is_ok() {
+        entries.push(entry);
+        next = unsafe { Process32NextW(snapshot, &mut entry) };
+    }
+
+    unsafe { CloseHandle(snapshot)? };
+
+    Ok(entries)
+}
+
+pub(crate) fn wide_to_string(wide: &[u16]) -> String {
+    let len = wide.iter().position(|&c| c == 0).unwrap_or(wide.len());
+    OsString::from_wide(&wide[..len])
+        .to_string_lossy()
+        .into_owned()
+}
@@ -0,0 +1,270 @@
+//! Waiting for a [GameProcess] to exit, e.g. to clean up, report a crash, or restart the game.
+//!
+//! # Example
+//! ```norun
+//! let watcher = process.watch_exit(|status| {
+//!     if status.is_exception() {
+//!         show_crash_report(status);
+//!     }
+//! })?;
+//! // Keep the watcher alive for as long as the callback should be able to run.
+//! ```
+use std::fmt;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use windows::Win32::Foundation::{HANDLE, WAIT_EVENT, WAIT_OBJECT_0, WAIT_TIMEOUT};
+use windows::Win32::System::Threading::{
+    CreateEventW, GetExitCodeProcess, INFINITE, SetEvent, WaitForMultipleObjects,
+    WaitForSingleObject,
+};
+
+use crate::patching::process::{BorrowedGameProcess, GameProcess, ProcessErrorKind, Result};
+
+/// The exit code of a process which has exited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExitStatus(pub u32);
+
+impl ExitStatus {
+    pub fn code(&self) -> u32 {
+        self.0
+    }
+
+    pub fn success(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether the code is an `NTSTATUS` error, as left behind by an unhandled exception, e.g. `0xC0000005` for an
+    /// access violation.
+    ///
+    /// Customer-defined codes are only counted for the well-known C++ and .NET exceptions, so negative exit codes
+    /// (`exit(-1)` leaves `0xFFFFFFFF`) aren't mistaken for crashes.
+    pub fn is_exception(&self) -> bool {
+        const SEVERITY_ERROR: u32 = 0xC000_0000;
+        const CUSTOMER: u32 = 0x2000_0000;
+        const STATUS_BREAKPOINT: u32 = 0x8000_0003;
+        const MSVC_CPP_EXCEPTION: u32 = 0xE06D_7363;
+        const CLR_EXCEPTION: u32 = 0xE043_4352;
+
+        match self.0 {
+            STATUS_BREAKPOINT | MSVC_CPP_EXCEPTION | CLR_EXCEPTION => true,
+            code => code & SEVERITY_ERROR == SEVERITY_ERROR && code & CUSTOMER == 0,
+        }
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_exception() {
+            write!(f, "exception {:#010X}", self.0)
+        } else {
+            write!(f, "exit code {}", self.0)
+        }
+    }
+}
+
+impl BorrowedGameProcess<'_> {
+    /// See [GameProcess::is_running].
+    pub fn is_running(&self) -> Result<bool> {
+        Ok(self.wait_for_exit(Some(Duration::ZERO))?.is_none())
+    }
+
+    /// See [GameProcess::exit_status].
+    pub fn exit_status(&self) -> Result<Option<ExitStatus>> {
+        self.wait_for_exit(Some(Duration::ZERO))
+    }
+
+    /// See [GameProcess::wait_for_exit].
+    pub fn wait_for_exit(&self, timeout: Option<Duration>) -> Result<Option<ExitStatus>> {
+        self.game_process().wait_for_exit(timeout)
+    }
+
+    /// See [GameProcess::watch_exit].
+    pub fn watch_exit(
+        &self,
+        on_exit: impl FnOnce(ExitStatus) + Send + 'static,
+    ) -> Result<ExitWatcher> {
+        self.game_process().watch_exit(on_exit)
+    }
+
+    /// A short-lived copy to call the implementations on [GameProcess], which must not escape.
+    fn game_process(&self) -> GameProcess {
+        GameProcess {
+            handle: self.as_raw_handle(),
+            pid: self.pid(),
+        }
+    }
+}
+
+impl GameProcess {
+    /// Whether the process is still running.
+    ///
+    /// The handle needs the `SYNCHRONIZE` access right.
+    pub fn is_running(&self) -> Result<bool> {
+        Ok(self.wait_for_exit(Some(Duration::ZERO))?.is_none())
+    }
+
+    /// The exit status, or [None] if the process is still running.
+    ///
+    /// The handle needs the `SYNCHRONIZE` and `PROCESS_QUERY_LIMITED_INFORMATION` access rights.
+    pub fn exit_status(&self) -> Result<Option<ExitStatus>> {
+        self.wait_for_exit(Some(Duration::ZERO))
+    }
+
+    /// Block until the process has exited, or the `timeout` elapsed.
+    ///
+    /// A `timeout` of [None] waits indefinitely.
+    ///
+    /// # Returns
+    ///
+    /// The exit status, or [None] if the process is still running after the `timeout`.
+    pub fn wait_for_exit(&self, timeout: Option<Duration>) -> Result<Option<ExitStatus>> {
+        let timeout = timeout.map_or(INFINITE, |timeout| {
+            timeout.as_millis().min(INFINITE as u128 - 1) as u32
+        });
+
+        match unsafe { WaitForSingleObject(self.handle, timeout) } {
+            // Only read once exited, a process can also exit with `STILL_ACTIVE` as its code.
+            WAIT_OBJECT_0 => {
+                let mut code = 0;
+                unsafe { GetExitCodeProcess(self.handle, &mut code)? };
+                Ok(Some(ExitStatus(code)))
+            }
+            WAIT_TIMEOUT => Ok(None),
+            _ => Err(ProcessErrorKind::OtherErr(
+                windows::core::Error::from_thread(),
+            )),
+        }
+    }
+
+    /// Call `on_exit` on a separate thread once the process exits.
+    ///
+    /// The process handle is duplicated, so this [GameProcess] doesn't need to outlive the returned [ExitWatcher].
+    pub fn watch_exit(
+        &self,
+        on_exit: impl FnOnce(ExitStatus) + Send + 'static,
+    ) -> Result<ExitWatcher> {
+        let process =
+            unsafe { BorrowedGameProcess::from_raw_handle(self.handle) }.try_to_owned()?;
+        let cancel = unsafe { CreateEventW(None, true, false, None)? };
+        let cancel = Arc::new(unsafe { OwnedHandle::from_raw_handle(cancel.0) });
+
+        let thread_cancel = cancel.clone();
+        let thread = std::thread::Builder::new()
+            .name(format!("exit-watcher-{}", self.pid))
+            .spawn(move || {
+                let handles = [
+                    process.as_raw_handle(),
+                    HANDLE(thread_cancel.as_raw_handle()),
+                ];
+
+                // Index 0 is the process, index 1 the cancellation by dropping the `ExitWatcher`.
+                const CANCELLED: WAIT_EVENT = WAIT_EVENT(WAIT_OBJECT_0.0 + 1);
+
+                match unsafe { WaitForMultipleObjects(&handles, false, INFINITE) } {
+                    WAIT_OBJECT_0 => {}
+                    CANCELLED => return,
+                    _ => {
+                        log::warn!(
+                            "Failed to wait for {}: {}",
+                            process.pid(),
+                            windows::core::Error::from_thread()
+                        );
+                        return;
+                    }
+                }
+
+                match process.borrowed().exit_status() {
+                    Ok(Some(status)) => on_exit(status),
+                    Ok(None) => {}
+                    Err(e) => log::warn!("Failed to get exit code of {}: {}", process.pid(), e),
+                }
+            })
+            .map_err(|e| ProcessErrorKind::Any(e.into()))?;
+
+        Ok(ExitWatcher {
+            thread: Some(thread),
+            cancel,
+        })
+    }
+}
+
+/// Watches a process for its exit, see [GameProcess::watch_exit].
+///
+/// Dropping the watcher cancels it, use [ExitWatcher::detach] to keep watching in the background.
+#[derive(Debug)]
+pub struct ExitWatcher {
+    thread: Option<JoinHandle<()>>,
+    cancel: Arc<OwnedHandle>,
+}
+
+impl ExitWatcher {
+    /// Whether the callback has run (or the watcher was cancelled).
+    pub fn is_finished(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_none_or(|thread| thread.is_finished())
+    }
+
+    /// Block until the process has exited and the callback has returned.
+    pub fn join(mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    /// Keep watching for as long as the current process runs, without holding on to the watcher.
+    pub fn detach(mut self) {
+        self.thread.take();
+    }
+}
+
+impl Drop for ExitWatcher {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = unsafe { SetEvent(HANDLE(self.cancel.as_raw_handle())) };
+            // Dropped from within the callback, the thread is about to finish and joining itself would deadlock.
+            if thread.thread().id() != std::thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExitStatus;
+
+    #[test]
+    fn success_is_only_zero() {
+        assert!(ExitStatus(0).success());
+        assert!(!ExitStatus(1).success());
+        assert!(!ExitStatus(0xC000_0005).success());
+    }
+
+    #[test]
+    fn detects_exceptions() {
+        for code in [
+            0xC000_0005,
+            0xC000_00FD,
+            0xC000_0409,
+            0x8000_0003,
+            0xE06D_7363,
+        ] {
+            assert!(ExitStatus(code).is_exception(), "{code:#X}");
+        }
+        // `exit(-1)`, `exit(-2)` and ordinary codes.
+        for code in [0xFFFF_FFFF, 0xFFFF_FFFE, 0, 1, 259] {
+            assert!(!ExitStatus(code).is_exception(), "{code:#X}");
+        }
+    }
+
+    #[test]
+    fn displays_codes_and_exceptions() {
+        assert_eq!(ExitStatus(0).to_string(), "exit code 0");
+        assert_eq!(ExitStatus(0xFFFF_FFFF).to_string(), "exit code 4294967295");
+        assert_eq!(ExitStatus(0xC000_0005).to_string(), "exception 0xC0000005");
+    }
+}
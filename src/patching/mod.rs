@@ -1,3 +1,5 @@
+pub mod discovery;
+pub mod exit;
 pub mod process;
 
 /// Can patch code in memory, so long as the pointers given are from the same memory space.
@@ -11,7 +13,19 @@ pub struct Patch {
     pub original_bytes: Box<[u8]>,
 }
 
+// SAFETY: The addresses are only ever dereferenced within the current process, on whichever thread.
+unsafe impl Send for LocalPatcher {}
+
 impl Patch {
+    /// Whether the memory at [Self::address] currently contains the patch bytes.
+    ///
+    /// # Safety
+    ///
+    /// [Self::address] must still be valid for reads.
+    pub unsafe fn is_enabled(&self) -> bool {
+        std::slice::from_raw_parts(self.address, self.patch_bytes.len()) == self.patch_bytes()
+    }
+
     fn original_bytes(&self) -> &[u8] {
         &*self.original_bytes
     }
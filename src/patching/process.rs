@@ -1,21 +1,37 @@
+use std::io::Read;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
 use std::ops::{Deref, DerefMut};
+#[cfg(feature = "launching")]
+use std::os::windows::io::AsRawHandle;
+use std::os::windows::io::{FromRawHandle, IntoRawHandle, OwnedHandle};
 use std::path::PathBuf;
 use std::time::Duration;
 use std::{ffi::OsString, mem, os::windows::ffi::OsStringExt};
 
+use eyre::WrapErr;
 use thiserror::Error;
-use windows::core::BOOL;
-use windows::Win32::Foundation::{CloseHandle, HANDLE, HMODULE, HWND, LPARAM};
+use windows::core::{BOOL, PWSTR};
+use windows::Win32::Foundation::{
+    CloseHandle, DuplicateHandle, DUPLICATE_SAME_ACCESS, HANDLE, HMODULE, HWND, LPARAM,
+};
 use windows::Win32::System::Diagnostics::Debug::{ReadProcessMemory, WriteProcessMemory};
 use windows::Win32::System::Diagnostics::ToolHelp::{
     CreateToolhelp32Snapshot, Module32FirstW, Module32NextW, MODULEENTRY32W, TH32CS_SNAPMODULE,
     TH32CS_SNAPMODULE32,
 };
+use windows::Win32::System::SystemInformation::{IMAGE_FILE_MACHINE, IMAGE_FILE_MACHINE_UNKNOWN};
+use windows::Win32::System::Threading::{
+    GetCurrentProcess, IsWow64Process2, OpenProcess, PROCESS_ACCESS_RIGHTS, PROCESS_NAME_WIN32,
+    QueryFullProcessImageNameW,
+};
 use windows::Win32::UI::WindowsAndMessaging::{
     EnumWindows, GetForegroundWindow, GetWindow, GetWindowTextW, GetWindowThreadProcessId,
     IsWindow, IsWindowVisible, GW_OWNER,
 };
 
+use crate::pe::{Machine, PeFile};
+
 pub type Result<T> = std::result::Result<T, ProcessErrorKind>;
 
 #[derive(Debug, Error)]
@@ -61,6 +77,46 @@ impl GameProcess {
         self.pid == unsafe { windows::Win32::System::Threading::GetCurrentProcessId() }
     }
 
+    /// The full path of the executable of this process.
+    pub fn image_path(&self) -> Result<PathBuf> {
+        let mut buffer = [0u16; 1024];
+        let mut len = buffer.len() as u32;
+
+        unsafe {
+            QueryFullProcessImageNameW(
+                self.handle,
+                PROCESS_NAME_WIN32,
+                PWSTR(buffer.as_mut_ptr()),
+                &mut len,
+            )?
+        };
+
+        Ok(PathBuf::from(OsString::from_wide(&buffer[..len as usize])))
+    }
+
+    /// The architecture this process runs as, e.g. [Machine::X86] for a WOW64 process on a 64-bit system, or
+    /// [Machine::X64] for an emulated x64 process on ARM64.
+    pub fn machine(&self) -> Result<Machine> {
+        let mut process_machine = IMAGE_FILE_MACHINE::default();
+        unsafe { IsWow64Process2(self.handle, &mut process_machine, None)? };
+
+        if process_machine != IMAGE_FILE_MACHINE_UNKNOWN {
+            return Ok(Machine::from_raw(process_machine.0));
+        }
+
+        // `UNKNOWN` if the process is not running under WOW64. It might still be emulated (x64 on ARM64), which isn't
+        // reported as WOW64, so the machine of its executable is used rather than the native one.
+        let path = self.image_path()?;
+        let mut header = Vec::with_capacity(0x1000);
+        std::fs::File::open(&path)
+            .and_then(|file| file.take(0x1000).read_to_end(&mut header))
+            .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        let pe = PeFile::parse(&header)
+            .wrap_err_with(|| format!("Invalid executable {}", path.display()))?;
+
+        Ok(pe.machine())
+    }
+
     /// Read from `ptr` into `buf` up to `buf.len()` bytes.
     ///
     /// If fewer than `buf.len()` bytes are read an error is returned.
@@ -298,6 +354,224 @@ impl GameProcess {
     }
 }
 
+/// A [GameProcess] owning its handle, which is closed on drop.
+///
+/// The handle is only handed out through [borrowed](Self::borrowed), whose lifetime keeps the process open, or
+/// explicitly through [as_raw_handle](Self::as_raw_handle).
+#[derive(Debug)]
+pub struct OwnedGameProcess(GameProcess);
+
+impl OwnedGameProcess {
+    /// Open the process with the given `pid` and `access` rights, e.g. `PROCESS_ALL_ACCESS`.
+    pub fn open(pid: u32, access: PROCESS_ACCESS_RIGHTS) -> Result<Self> {
+        let handle = unsafe { OpenProcess(access, false, pid)? };
+
+        Ok(OwnedGameProcess(GameProcess { handle, pid }))
+    }
+
+    /// Take ownership of the given process handle.
+    ///
+    /// # Safety
+    ///
+    /// The `handle` must be a valid process handle, which is not closed by anyone else.
+    pub unsafe fn from_raw_handle(handle: HANDLE) -> Self {
+        OwnedGameProcess(GameProcess::new(handle))
+    }
+
+    /// Release ownership of the handle, which the caller is now responsible for closing.
+    pub fn into_raw_handle(self) -> HANDLE {
+        let handle = self.0.handle;
+        mem::forget(self);
+        handle
+    }
+
+    /// The raw handle, which stays owned by `self`.
+    pub fn as_raw_handle(&self) -> HANDLE {
+        self.0.handle
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.0.pid
+    }
+
+    pub fn borrowed(&self) -> BorrowedGameProcess<'_> {
+        BorrowedGameProcess(self.0, PhantomData)
+    }
+
+    /// Duplicate the handle, with the same access rights.
+    pub fn try_clone(&self) -> Result<Self> {
+        let mut duplicate = HANDLE::default();
+
+        unsafe {
+            DuplicateHandle(
+                GetCurrentProcess(),
+                self.0.handle,
+                GetCurrentProcess(),
+                &mut duplicate,
+                0,
+                false,
+                DUPLICATE_SAME_ACCESS,
+            )?;
+        }
+
+        Ok(OwnedGameProcess(GameProcess {
+            handle: duplicate,
+            pid: self.0.pid,
+        }))
+    }
+}
+
+// SAFETY: Process handles are valid on any thread of the current process.
+unsafe impl Send for OwnedGameProcess {}
+
+impl Drop for OwnedGameProcess {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseHandle(self.0.handle) };
+    }
+}
+
+impl From<OwnedHandle> for OwnedGameProcess {
+    fn from(handle: OwnedHandle) -> Self {
+        unsafe { Self::from_raw_handle(HANDLE(handle.into_raw_handle())) }
+    }
+}
+
+impl From<OwnedGameProcess> for OwnedHandle {
+    fn from(process: OwnedGameProcess) -> Self {
+        unsafe { OwnedHandle::from_raw_handle(process.into_raw_handle().0) }
+    }
+}
+
+#[cfg(feature = "launching")]
+impl From<dll_syringe::process::OwnedProcess> for OwnedGameProcess {
+    fn from(process: dll_syringe::process::OwnedProcess) -> Self {
+        unsafe { Self::from_raw_handle(HANDLE(process.into_raw_handle())) }
+    }
+}
+
+#[cfg(feature = "launching")]
+impl From<OwnedGameProcess> for dll_syringe::process::OwnedProcess {
+    fn from(process: OwnedGameProcess) -> Self {
+        unsafe { dll_syringe::process::OwnedProcess::from_raw_handle(process.into_raw_handle().0) }
+    }
+}
+
+/// A process handle borrowed from an [OwnedGameProcess] (or any other owner), valid for `'a`.
+///
+/// Offers the methods of [GameProcess], but never hands one out: being `Copy`, it could outlive the owner and be
+/// used after the handle is closed.
+#[derive(Debug, Clone, Copy)]
+pub struct BorrowedGameProcess<'a>(GameProcess, PhantomData<&'a OwnedGameProcess>);
+
+impl BorrowedGameProcess<'_> {
+    /// Borrow the given process handle.
+    ///
+    /// # Safety
+    ///
+    /// The `handle` must be a valid process handle, which stays open for the chosen lifetime.
+    pub unsafe fn from_raw_handle(handle: HANDLE) -> Self {
+        BorrowedGameProcess(GameProcess::new(handle), PhantomData)
+    }
+
+    /// The current process, its pseudo handle is always valid.
+    pub fn current() -> BorrowedGameProcess<'static> {
+        BorrowedGameProcess(GameProcess::current_process(), PhantomData)
+    }
+
+    /// Open a new handle to the same process, with the same access rights.
+    pub fn try_to_owned(&self) -> Result<OwnedGameProcess> {
+        ManuallyDrop::new(OwnedGameProcess(self.0)).try_clone()
+    }
+
+    /// The raw handle, which stays owned by the owner of `self`.
+    pub fn as_raw_handle(&self) -> HANDLE {
+        self.0.handle
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.0.pid
+    }
+
+    /// See [GameProcess::is_current].
+    pub fn is_current(&self) -> bool {
+        self.0.is_current()
+    }
+
+    /// See [GameProcess::image_path].
+    pub fn image_path(&self) -> Result<PathBuf> {
+        self.0.image_path()
+    }
+
+    /// See [GameProcess::machine].
+    pub fn machine(&self) -> Result<Machine> {
+        self.0.machine()
+    }
+
+    /// See [GameProcess::read_absolute_buffer].
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid within the process' memory space.
+    pub unsafe fn read_absolute_buffer(&self, ptr: *mut u8, buffer: &mut [u8]) -> Result<usize> {
+        self.0.read_absolute_buffer(ptr, buffer)
+    }
+
+    /// See [GameProcess::write_absolute_buffer].
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure the `ptr` is within the bounds of the process.
+    pub unsafe fn write_absolute_buffer(&self, ptr: *mut u8, buffer: &[u8]) -> Result<()> {
+        let mut process = self.0;
+        process.write_absolute_buffer(ptr, buffer)
+    }
+
+    /// See [GameProcess::get_modules].
+    pub fn get_modules(&self) -> Result<Vec<Module>> {
+        self.0.get_modules()
+    }
+
+    /// See [GameProcess::get_module].
+    pub fn get_module(&self, module_name: &str) -> Result<Module> {
+        self.0.get_module(module_name)
+    }
+
+    /// See [GameProcess::get_base_module].
+    pub fn get_base_module(&self) -> Result<Module> {
+        self.0.get_base_module()
+    }
+
+    /// See [GameProcess::get_main_window].
+    pub fn get_main_window(&self) -> Option<Window> {
+        self.0.get_main_window()
+    }
+
+    /// See [GameProcess::get_windows].
+    pub fn get_windows(&self) -> Result<Vec<Window>> {
+        self.0.get_windows()
+    }
+}
+
+impl<'a> From<&'a OwnedGameProcess> for BorrowedGameProcess<'a> {
+    fn from(process: &'a OwnedGameProcess) -> Self {
+        process.borrowed()
+    }
+}
+
+#[cfg(feature = "launching")]
+impl<'a> From<dll_syringe::process::BorrowedProcess<'a>> for BorrowedGameProcess<'a> {
+    fn from(process: dll_syringe::process::BorrowedProcess<'a>) -> Self {
+        unsafe { BorrowedGameProcess::from_raw_handle(HANDLE(process.as_raw_handle())) }
+    }
+}
+
+#[cfg(feature = "launching")]
+impl<'a> From<&'a dll_syringe::process::OwnedProcess> for BorrowedGameProcess<'a> {
+    fn from(process: &'a dll_syringe::process::OwnedProcess) -> Self {
+        unsafe { BorrowedGameProcess::from_raw_handle(HANDLE(process.as_raw_handle())) }
+    }
+}
+
 #[derive(Default, Debug, Clone, Copy)]
 pub struct Window(pub HWND);
 
@@ -0,0 +1,193 @@
+use crate::pe::{DataDirectoryKind, PeErrorKind, PeFile, Result, add_rva, read_bytes, table_rva};
+
+/// A single entry of a PE export table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    pub ordinal: u16,
+    /// The exported name, [None] for ordinal-only (`NONAME`) exports.
+    pub name: Option<String>,
+    pub target: ExportTarget,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportTarget {
+    /// The RVA of the exported function or variable.
+    Rva(u32),
+    /// The export is forwarded to a different DLL, e.g. `NTDLL.RtlAllocateHeap`.
+    Forwarder(String),
+}
+
+impl PeFile<'_> {
+    /// The name of the DLL as stored in its export directory, if it has one.
+    pub fn export_dll_name(&self) -> Result<Option<String>> {
+        let Some(directory) = self.data_directory(DataDirectoryKind::Export) else {
+            return Ok(None);
+        };
+
+        let name_rva = self.read_u32_at(add_rva(directory.rva, 12)?)?;
+
+        Ok(Some(self.read_str_at(name_rva)?.to_owned()))
+    }
+
+    /// Parse the export table, sorted by ordinal.
+    ///
+    /// Returns an empty list if the file has no export directory.
+    pub fn exports(&self) -> Result<Vec<Export>> {
+        let Some(directory) = self.data_directory(DataDirectoryKind::Export) else {
+            return Ok(Vec::new());
+        };
+
+        let ordinal_base = self.read_u32_at(add_rva(directory.rva, 16)?)?;
+        let number_of_functions = self.read_u32_at(add_rva(directory.rva, 20)?)?;
+        let number_of_names = self.read_u32_at(add_rva(directory.rva, 24)?)?;
+        let address_of_functions = self.read_u32_at(add_rva(directory.rva, 28)?)?;
+        let address_of_names = self.read_u32_at(add_rva(directory.rva, 32)?)?;
+        let address_of_name_ordinals = self.read_u32_at(add_rva(directory.rva, 36)?)?;
+
+        // A corrupt count would otherwise allocate gigabytes, the address table has to fit within the file.
+        read_bytes(
+            self.data(),
+            self.rva_to_offset(address_of_functions)?,
+            (number_of_functions as usize).saturating_mul(4),
+        )?;
+
+        let mut names = vec![None; number_of_functions as usize];
+
+        for i in 0..number_of_names {
+            let name_rva = self.read_u32_at(table_rva(address_of_names, i, 4)?)?;
+            let index = self.read_u16_at(table_rva(address_of_name_ordinals, i, 2)?)? as usize;
+
+            if let Some(slot) = names.get_mut(index) {
+                *slot = Some(self.read_str_at(name_rva)?.to_owned());
+            }
+        }
+
+        let mut result = Vec::with_capacity(names.len());
+
+        for (i, name) in (0..number_of_functions).zip(names) {
+            let function_rva = self.read_u32_at(table_rva(address_of_functions, i, 4)?)?;
+
+            // Unused slot in the export address table.
+            if function_rva == 0 {
+                continue;
+            }
+
+            let target = if directory.contains(function_rva) {
+                ExportTarget::Forwarder(self.read_str_at(function_rva)?.to_owned())
+            } else {
+                ExportTarget::Rva(function_rva)
+            };
+
+            let ordinal = ordinal_base
+                .checked_add(i)
+                .and_then(|ordinal| u16::try_from(ordinal).ok())
+                .ok_or(PeErrorKind::InvalidOrdinal {
+                    base: ordinal_base,
+                    index: i,
+                })?;
+
+            result.push(Export {
+                ordinal,
+                name,
+                target,
+            });
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pe::testing::{SECTION_RVA, export_fixture, put_u32};
+    use crate::pe::{DataDirectoryKind, Export, ExportTarget, PeErrorKind, PeFile};
+
+    #[test]
+    fn parses_names_ordinals_and_forwarders() {
+        let data = export_fixture().build();
+        let pe = PeFile::parse(&data).unwrap();
+
+        assert_eq!(pe.export_dll_name().unwrap().as_deref(), Some("test.dll"));
+        assert_eq!(
+            pe.exports().unwrap(),
+            vec![
+                Export {
+                    ordinal: 5,
+                    name: Some("Alpha".into()),
+                    target: ExportTarget::Rva(0x2000),
+                },
+                Export {
+                    ordinal: 7,
+                    name: Some("Forwarded".into()),
+                    target: ExportTarget::Forwarder("NTDLL.RtlAllocateHeap".into()),
+                },
+                Export {
+                    ordinal: 8,
+                    name: None,
+                    target: ExportTarget::Rva(0x2010),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_function_count() {
+        let mut data = export_fixture().build();
+        let pe = PeFile::parse(&data).unwrap();
+        let directory = pe.rva_to_offset(SECTION_RVA).unwrap();
+        put_u32(&mut data, directory + 20, u32::MAX);
+
+        let pe = PeFile::parse(&data).unwrap();
+        assert!(matches!(pe.exports(), Err(PeErrorKind::OutOfBounds { .. })));
+    }
+
+    #[test]
+    fn rejects_overflowing_rvas() {
+        let data = export_fixture()
+            .directory(DataDirectoryKind::Export, u32::MAX - 8, 0x100)
+            .build();
+        let pe = PeFile::parse(&data).unwrap();
+        assert!(matches!(
+            pe.export_dll_name(),
+            Err(PeErrorKind::OutOfBounds { .. })
+        ));
+        assert!(matches!(pe.exports(), Err(PeErrorKind::OutOfBounds { .. })));
+    }
+
+    #[test]
+    fn rejects_ordinals_above_u16() {
+        let mut data = export_fixture().build();
+        let directory = PeFile::parse(&data)
+            .unwrap()
+            .rva_to_offset(SECTION_RVA)
+            .unwrap();
+        put_u32(&mut data, directory + 16, u32::MAX - 1);
+
+        let pe = PeFile::parse(&data).unwrap();
+        assert!(matches!(
+            pe.exports(),
+            Err(PeErrorKind::InvalidOrdinal { .. })
+        ));
+
+        put_u32(&mut data, directory + 16, 0xFFFE);
+        let pe = PeFile::parse(&data).unwrap();
+        assert!(matches!(
+            pe.exports(),
+            Err(PeErrorKind::InvalidOrdinal {
+                base: 0xFFFE,
+                index: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn no_export_directory() {
+        let data = export_fixture()
+            .directory(DataDirectoryKind::Export, 0, 0)
+            .build();
+        let pe = PeFile::parse(&data).unwrap();
+
+        assert_eq!(pe.export_dll_name().unwrap(), None);
+        assert!(pe.exports().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::pe::{DataDirectoryKind, Layout, PeErrorKind, PeFile, Result, add_rva, table_rva};
+
+/// `IMAGE_SCN_MEM_EXECUTE`
+pub const SECTION_EXECUTE: u32 = 0x2000_0000;
+/// `IMAGE_SCN_MEM_READ`
+pub const SECTION_READ: u32 = 0x4000_0000;
+/// `IMAGE_SCN_MEM_WRITE`
+pub const SECTION_WRITE: u32 = 0x8000_0000;
+
+impl PeFile<'_> {
+    /// Lay the file out the way the loader maps it: headers at the start, each section at its virtual address, and
+    /// zero-filled up to [size_of_image](Self::size_of_image).
+    pub fn map_image(&self) -> Result<Vec<u8>> {
+        let size_of_image = self.size_of_image() as usize;
+
+        if self.layout() == Layout::Image {
+            return self.data().get(..size_of_image).map(<[u8]>::to_vec).ok_or(
+                PeErrorKind::OutOfBounds {
+                    offset: 0,
+                    len: size_of_image,
+                },
+            );
+        }
+
+        let mut image = vec![0u8; size_of_image];
+
+        let headers = (self.size_of_headers() as usize)
+            .min(size_of_image)
+            .min(self.data().len());
+        image[..headers].copy_from_slice(&self.data()[..headers]);
+
+        for section in self.sections() {
+            let len = if section.virtual_size == 0 {
+                section.raw_size
+            } else {
+                section.raw_size.min(section.virtual_size)
+            } as usize;
+
+            let source = section.raw_offset as usize;
+            let destination = section.virtual_address as usize;
+
+            let bytes = source
+                .checked_add(len)
+                .and_then(|end| self.data().get(source..end))
+                .ok_or(PeErrorKind::OutOfBounds {
+                    offset: source,
+                    len,
+                })?;
+            destination
+                .checked_add(len)
+                .and_then(|end| image.get_mut(destination..end))
+                .ok_or(PeErrorKind::OutOfBounds {
+                    offset: destination,
+                    len,
+                })?
+                .copy_from_slice(bytes);
+        }
+
+        Ok(image)
+    }
+
+    /// The RVAs of the TLS callbacks, in the order they have to be called.
+    ///
+    /// The callback array holds virtual addresses for the preferred [image_base](Self::image_base), which are
+    /// converted to RVAs.
+    pub fn tls_callbacks(&self) -> Result<Vec<u32>> {
+        let Some(directory) = self.data_directory(DataDirectoryKind::Tls) else {
+            return Ok(Vec::new());
+        };
+
+        let (callbacks_va, pointer_size) = if self.is_64_bit() {
+            (self.read_u64_at(add_rva(directory.rva, 24)?)?, 8)
+        } else {
+            (self.read_u32_at(add_rva(directory.rva, 12)?)? as u64, 4)
+        };
+
+        if callbacks_va == 0 {
+            return Ok(Vec::new());
+        }
+
+        let to_rva = |va: u64| {
+            va.checked_sub(self.image_base())
+                .and_then(|rva| u32::try_from(rva).ok())
+                .ok_or(PeErrorKind::InvalidRva(va as u32))
+        };
+
+        let callbacks_rva = to_rva(callbacks_va)?;
+        let mut callbacks = Vec::new();
+
+        for index in 0.. {
+            let slot = table_rva(callbacks_rva, index, pointer_size)?;
+            let callback = if self.is_64_bit() {
+                self.read_u64_at(slot)?
+            } else {
+                self.read_u32_at(slot)? as u64
+            };
+
+            if callback == 0 {
+                break;
+            }
+
+            callbacks.push(to_rva(callback)?);
+        }
+
+        Ok(callbacks)
+    }
+
+    /// The size of the implicit TLS data of every thread (`__declspec(thread)`, or `#[thread_local]` as used by
+    /// Rust's `thread_local!` on MSVC), `0` if there is none.
+    ///
+    /// Only the loader sets up implicit TLS, so modules which use it can't be mapped by hand.
+    pub fn implicit_tls_size(&self) -> Result<u64> {
+        let Some(directory) = self.data_directory(DataDirectoryKind::Tls) else {
+            return Ok(0);
+        };
+
+        let (start, end, zero_fill) = if self.is_64_bit() {
+            (
+                self.read_u64_at(directory.rva)?,
+                self.read_u64_at(add_rva(directory.rva, 8)?)?,
+                self.read_u32_at(add_rva(directory.rva, 32)?)?,
+            )
+        } else {
+            (
+                self.read_u32_at(directory.rva)? as u64,
+                self.read_u32_at(add_rva(directory.rva, 4)?)? as u64,
+                self.read_u32_at(add_rva(directory.rva, 16)?)?,
+            )
+        };
+
+        Ok(end.saturating_sub(start) + zero_fill as u64)
+    }
+}
+
+/// Write a pointer sized `value` at the given `rva` of a mapped `image`, e.g. to fill an import address table slot.
+pub fn write_pointer(image: &mut [u8], rva: u32, value: u64, is_64_bit: bool) -> Result<()> {
+    let offset = rva as usize;
+    let bytes = value.to_le_bytes();
+    let len = if is_64_bit { 8 } else { 4 };
+
+    image
+        .get_mut(offset..offset + len)
+        .ok_or(PeErrorKind::OutOfBounds { offset, len })?
+        .copy_from_slice(&bytes[..len]);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pe::testing::{SECTION_RVA, TestPe, put_u32, put_u64};
+    use crate::pe::{DataDirectoryKind, Machine, PeErrorKind, PeFile, write_pointer};
+
+    #[test]
+    fn maps_headers_and_sections() {
+        let data = TestPe::new(Machine::X64).section(vec![1, 2, 3, 4]).build();
+        let pe = PeFile::parse(&data).unwrap();
+        let image = pe.map_image().unwrap();
+
+        assert_eq!(image.len(), 0x2000);
+        assert_eq!(image[..0x200], data[..0x200]);
+        assert_eq!(image[0x1000..0x1004], [1, 2, 3, 4]);
+        assert!(image[0x1004..].iter().all(|&byte| byte == 0));
+
+        // A mapped image is taken as is.
+        let pe = PeFile::parse_image(&image).unwrap();
+        assert_eq!(pe.map_image().unwrap(), image);
+    }
+
+    #[test]
+    fn rejects_section_outside_of_file() {
+        let pe = TestPe::new(Machine::X64).section(vec![1, 2, 3, 4]);
+        let mut data = pe.build();
+        put_u32(&mut data, pe.section_header() + 20, u32::MAX - 0x100);
+
+        let pe = PeFile::parse(&data).unwrap();
+        assert!(matches!(
+            pe.map_image(),
+            Err(PeErrorKind::OutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn tls_callbacks_are_converted_to_rvas() {
+        let pe = TestPe::new(Machine::X64);
+        let image_base = pe.image_base();
+
+        let mut section = vec![0u8; 0x100];
+        put_u64(&mut section, 24, image_base + (SECTION_RVA + 0x40) as u64);
+        put_u64(&mut section, 0x40, image_base + 0x2000);
+        put_u64(&mut section, 0x48, image_base + 0x2010);
+
+        let data = pe
+            .section(section)
+            .directory(DataDirectoryKind::Tls, SECTION_RVA, 40)
+            .build();
+        let pe = PeFile::parse(&data).unwrap();
+
+        assert_eq!(pe.tls_callbacks().unwrap(), vec![0x2000, 0x2010]);
+        assert_eq!(pe.implicit_tls_size().unwrap(), 0);
+    }
+
+    #[test]
+    fn implicit_tls_size() {
+        let pe = TestPe::new(Machine::X86);
+        let image_base = pe.image_base() as u32;
+
+        let mut section = vec![0u8; 0x100];
+        put_u32(&mut section, 0, image_base + SECTION_RVA + 0x80);
+        put_u32(&mut section, 4, image_base + SECTION_RVA + 0x88);
+        put_u32(&mut section, 16, 0x10);
+
+        let data = pe
+            .section(section)
+            .directory(DataDirectoryKind::Tls, SECTION_RVA, 24)
+            .build();
+        let pe = PeFile::parse(&data).unwrap();
+
+        assert_eq!(pe.implicit_tls_size().unwrap(), 0x18);
+        assert!(pe.tls_callbacks().unwrap().is_empty());
+
+        let data = TestPe::new(Machine::X64).build();
+        let pe = PeFile::parse(&data).unwrap();
+        assert_eq!(pe.implicit_tls_size().unwrap(), 0);
+    }
+
+    #[test]
+    fn writes_pointers_of_either_size() {
+        let mut image = vec![0xFFu8; 0x10];
+
+        write_pointer(&mut image, 0, 0x1122_3344, false).unwrap();
+        write_pointer(&mut image, 8, 0x1122_3344_5566_7788, true).unwrap();
+        assert_eq!(image[..8], [0x44, 0x33, 0x22, 0x11, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(image[8..], 0x1122_3344_5566_7788u64.to_le_bytes());
+
+        assert!(matches!(
+            write_pointer(&mut image, 0xC, 0, true),
+            Err(PeErrorKind::OutOfBounds { .. })
+        ));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::pe::{DataDirectoryKind, PeFile, Result, add_rva, table_rva};
+
+/// All functions imported from a single DLL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    pub dll_name: String,
+    pub thunks: Vec<ImportThunk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportThunk {
+    /// The RVA of the import address table slot, which receives the address of the imported symbol.
+    pub iat_rva: u32,
+    pub symbol: ImportSymbol,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportSymbol {
+    Name { hint: u16, name: String },
+    Ordinal(u16),
+}
+
+impl PeFile<'_> {
+    /// Parse the import directory.
+    ///
+    /// Returns an empty list if the file has no import directory.
+    pub fn imports(&self) -> Result<Vec<Import>> {
+        let Some(directory) = self.data_directory(DataDirectoryKind::Import) else {
+            return Ok(Vec::new());
+        };
+
+        let mut imports = Vec::new();
+
+        for descriptor_index in 0.. {
+            let descriptor = table_rva(directory.rva, descriptor_index, 20)?;
+            let original_first_thunk = self.read_u32_at(descriptor)?;
+            let name_rva = self.read_u32_at(add_rva(descriptor, 12)?)?;
+            let first_thunk = self.read_u32_at(add_rva(descriptor, 16)?)?;
+
+            // The table is terminated by an all-zero descriptor.
+            if name_rva == 0 && first_thunk == 0 {
+                break;
+            }
+
+            // Bound imports only have the IAT, which still holds the lookup entries on disk.
+            let lookup_table = if original_first_thunk != 0 {
+                original_first_thunk
+            } else {
+                first_thunk
+            };
+
+            let mut thunks = Vec::new();
+            let entry_size = if self.is_64_bit() { 8 } else { 4 };
+
+            for index in 0.. {
+                let slot = table_rva(lookup_table, index, entry_size)?;
+                let (entry, by_ordinal) = if self.is_64_bit() {
+                    let entry = self.read_u64_at(slot)?;
+                    (entry, entry & (1 << 63) != 0)
+                } else {
+                    let entry = self.read_u32_at(slot)? as u64;
+                    (entry, entry & (1 << 31) != 0)
+                };
+
+                if entry == 0 {
+                    break;
+                }
+
+                let symbol = if by_ordinal {
+                    ImportSymbol::Ordinal(entry as u16)
+                } else {
+                    let hint_name_rva = entry as u32;
+                    ImportSymbol::Name {
+                        hint: self.read_u16_at(hint_name_rva)?,
+                        name: self.read_str_at(add_rva(hint_name_rva, 2)?)?.to_owned(),
+                    }
+                };
+
+                thunks.push(ImportThunk {
+                    iat_rva: table_rva(first_thunk, index, entry_size)?,
+                    symbol,
+                });
+            }
+
+            imports.push(Import {
+                dll_name: self.read_str_at(name_rva)?.to_owned(),
+                thunks,
+            });
+        }
+
+        Ok(imports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pe::testing::{SECTION_RVA, TestPe, put_str, put_u16, put_u32, put_u64};
+    use crate::pe::{
+        DataDirectoryKind, Import, ImportSymbol, ImportThunk, Machine, PeErrorKind, PeFile,
+    };
+
+    fn rva(offset: u32) -> u32 {
+        SECTION_RVA + offset
+    }
+
+    /// Imports `LoadLibraryW` (hint `0x1F`) and ordinal `12` from `kernel32.dll`, with the import descriptor at the
+    /// start of the section, the lookup table at `0x40`, and the IAT at `0x60`.
+    fn import_fixture(machine: Machine, original_first_thunk: bool) -> TestPe {
+        let mut section = vec![0u8; 0x100];
+        if original_first_thunk {
+            put_u32(&mut section, 0, rva(0x40));
+        }
+        put_u32(&mut section, 12, rva(0x80));
+        put_u32(&mut section, 16, rva(0x60));
+
+        for table in [0x40, 0x60] {
+            if machine == Machine::X86 {
+                put_u32(&mut section, table, rva(0x90));
+                put_u32(&mut section, table + 4, 0x8000_0000 | 12);
+            } else {
+                put_u64(&mut section, table, rva(0x90) as u64);
+                put_u64(&mut section, table + 8, 0x8000_0000_0000_0000 | 12);
+            }
+        }
+
+        put_str(&mut section, 0x80, "kernel32.dll");
+        put_u16(&mut section, 0x90, 0x1F);
+        put_str(&mut section, 0x92, "LoadLibraryW");
+
+        TestPe::new(machine)
+            .section(section)
+            .directory(DataDirectoryKind::Import, SECTION_RVA, 40)
+    }
+
+    fn expected(entry_size: u32) -> Vec<Import> {
+        vec![Import {
+            dll_name: "kernel32.dll".into(),
+            thunks: vec![
+                ImportThunk {
+                    iat_rva: rva(0x60),
+                    symbol: ImportSymbol::Name {
+                        hint: 0x1F,
+                        name: "LoadLibraryW".into(),
+                    },
+                },
+                ImportThunk {
+                    iat_rva: rva(0x60 + entry_size),
+                    symbol: ImportSymbol::Ordinal(12),
+                },
+            ],
+        }]
+    }
+
+    #[test]
+    fn parses_named_and_ordinal_imports() {
+        let data = import_fixture(Machine::X64, true).build();
+        let pe = PeFile::parse(&data).unwrap();
+
+        assert_eq!(pe.imports().unwrap(), expected(8));
+    }
+
+    #[test]
+    fn parses_bound_imports_without_lookup_table() {
+        let data = import_fixture(Machine::X86, false).build();
+        let pe = PeFile::parse(&data).unwrap();
+
+        assert_eq!(pe.imports().unwrap(), expected(4));
+    }
+
+    #[test]
+    fn rejects_overflowing_thunk_rva() {
+        let mut data = import_fixture(Machine::X64, true).build();
+        let pe = PeFile::parse(&data).unwrap();
+        let descriptor = pe.rva_to_offset(SECTION_RVA).unwrap();
+        put_u32(&mut data, descriptor + 16, u32::MAX - 4);
+
+        let pe = PeFile::parse(&data).unwrap();
+        assert!(matches!(pe.imports(), Err(PeErrorKind::OutOfBounds { .. })));
+    }
+
+    #[test]
+    fn no_import_directory() {
+        let data = TestPe::new(Machine::X64).build();
+        let pe = PeFile::parse(&data).unwrap();
+
+        assert!(pe.imports().unwrap().is_empty());
+    }
+}
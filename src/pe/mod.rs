@@ -0,0 +1,407 @@
+//! Minimal, platform independent, parsing of Portable Executable (PE) files.
+//!
+//! Only the parts of the format which are needed for proxying and injecting DLLs are supported.
+//! As this module has no Windows dependencies it can also be used from build scripts on other hosts.
+use thiserror::Error;
+
+pub use exports::*;
+pub use image::*;
+pub use imports::*;
+pub use relocations::*;
+
+mod exports;
+mod image;
+mod imports;
+mod relocations;
+#[cfg(test)]
+pub(crate) mod testing;
+
+pub type Result<T> = std::result::Result<T, PeErrorKind>;
+
+#[derive(Debug, Error)]
+pub enum PeErrorKind {
+    #[error("Missing `MZ` DOS signature")]
+    InvalidDosSignature,
+
+    #[error("Missing `PE\\0\\0` signature")]
+    InvalidPeSignature,
+
+    #[error("Unknown optional header magic: {0:#X}")]
+    UnknownOptionalHeader(u16),
+
+    #[error("Read of {len} bytes at offset {offset:#X} is out of bounds")]
+    OutOfBounds { offset: usize, len: usize },
+
+    #[error("RVA is not backed by any section: {0:#X}")]
+    InvalidRva(u32),
+
+    #[error("Invalid string at RVA: {0:#X}")]
+    InvalidString(u32),
+
+    #[error("Unsupported base relocation type: {0}")]
+    UnsupportedRelocation(u8),
+
+    #[error("Export ordinal out of range, base {base} index {index}")]
+    InvalidOrdinal { base: u32, index: u32 },
+}
+
+/// The target architecture of a PE file, as stored in its COFF header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Machine {
+    X86,
+    X64,
+    Arm64,
+    Unknown(u16),
+}
+
+impl Machine {
+    pub fn from_raw(machine: u16) -> Self {
+        match machine {
+            0x014C => Machine::X86,
+            0x8664 => Machine::X64,
+            0xAA64 => Machine::Arm64,
+            other => Machine::Unknown(other),
+        }
+    }
+}
+
+/// How the PE bytes are laid out in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// The raw file as it is stored on disk, sections are found at their `PointerToRawData`.
+    File,
+    /// A module mapped by the loader, sections are found at their `VirtualAddress`.
+    Image,
+}
+
+/// Indices into the data directory table of the optional header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataDirectoryKind {
+    Export = 0,
+    Import = 1,
+    Exception = 3,
+    BaseRelocation = 5,
+    Tls = 9,
+    ImportAddressTable = 12,
+    DelayImport = 13,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DataDirectory {
+    pub rva: u32,
+    pub size: u32,
+}
+
+impl DataDirectory {
+    /// Whether the given `rva` lies within this directory.
+    pub fn contains(&self, rva: u32) -> bool {
+        rva >= self.rva && rva - self.rva < self.size
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub name: String,
+    pub virtual_address: u32,
+    pub virtual_size: u32,
+    pub raw_offset: u32,
+    pub raw_size: u32,
+    pub characteristics: u32,
+}
+
+impl Section {
+    fn contains(&self, rva: u32) -> bool {
+        let size = self.virtual_size.max(self.raw_size);
+        rva >= self.virtual_address && rva - self.virtual_address < size
+    }
+}
+
+/// A parsed view over the headers of a PE file.
+///
+/// The directories (exports, imports, ...) are only parsed on request.
+#[derive(Debug, Clone)]
+pub struct PeFile<'a> {
+    data: &'a [u8],
+    layout: Layout,
+    machine: Machine,
+    characteristics: u16,
+    is_64_bit: bool,
+    image_base: u64,
+    entry_point: u32,
+    size_of_image: u32,
+    size_of_headers: u32,
+    data_directories: Vec<DataDirectory>,
+    sections: Vec<Section>,
+}
+
+impl<'a> PeFile<'a> {
+    /// Parse a PE file as it is stored on disk.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        Self::parse_with_layout(data, Layout::File)
+    }
+
+    /// Parse a PE file which has been mapped into memory by the loader (e.g., read from a running process).
+    pub fn parse_image(data: &'a [u8]) -> Result<Self> {
+        Self::parse_with_layout(data, Layout::Image)
+    }
+
+    pub fn parse_with_layout(data: &'a [u8], layout: Layout) -> Result<Self> {
+        if read_u16(data, 0)? != 0x5A4D {
+            return Err(PeErrorKind::InvalidDosSignature);
+        }
+
+        let nt_offset = read_u32(data, 0x3C)? as usize;
+
+        if read_u32(data, nt_offset)? != 0x0000_4550 {
+            return Err(PeErrorKind::InvalidPeSignature);
+        }
+
+        let file_header = nt_offset + 4;
+        let machine = Machine::from_raw(read_u16(data, file_header)?);
+        let number_of_sections = read_u16(data, file_header + 2)? as usize;
+        let size_of_optional_header = read_u16(data, file_header + 16)? as usize;
+        let characteristics = read_u16(data, file_header + 18)?;
+
+        let optional_header = file_header + 20;
+        let (is_64_bit, image_base, directories_offset) = match read_u16(data, optional_header)? {
+            0x10B => (
+                false,
+                read_u32(data, optional_header + 28)? as u64,
+                optional_header + 96,
+            ),
+            0x20B => (
+                true,
+                read_u64(data, optional_header + 24)?,
+                optional_header + 112,
+            ),
+            magic => return Err(PeErrorKind::UnknownOptionalHeader(magic)),
+        };
+
+        let entry_point = read_u32(data, optional_header + 16)?;
+        let size_of_image = read_u32(data, optional_header + 56)?;
+        let size_of_headers = read_u32(data, optional_header + 60)?;
+        let number_of_directories = read_u32(data, directories_offset - 4)?.min(16) as usize;
+
+        let data_directories = (0..number_of_directories)
+            .map(|i| {
+                let offset = directories_offset + i * 8;
+                Ok(DataDirectory {
+                    rva: read_u32(data, offset)?,
+                    size: read_u32(data, offset + 4)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let section_table = optional_header + size_of_optional_header;
+        let sections = (0..number_of_sections)
+            .map(|i| {
+                let offset = section_table + i * 40;
+                let name = read_bytes(data, offset, 8)?;
+                let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+
+                Ok(Section {
+                    name: String::from_utf8_lossy(&name[..name_len]).into_owned(),
+                    virtual_size: read_u32(data, offset + 8)?,
+                    virtual_address: read_u32(data, offset + 12)?,
+                    raw_size: read_u32(data, offset + 16)?,
+                    raw_offset: read_u32(data, offset + 20)?,
+                    characteristics: read_u32(data, offset + 36)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(PeFile {
+            data,
+            layout,
+            machine,
+            characteristics,
+            is_64_bit,
+            image_base,
+            entry_point,
+            size_of_image,
+            size_of_headers,
+            data_directories,
+            sections,
+        })
+    }
+
+    /// The underlying bytes of this PE file.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn machine(&self) -> Machine {
+        self.machine
+    }
+
+    /// Whether this is a `PE32+` file, as opposed to a `PE32` file.
+    pub fn is_64_bit(&self) -> bool {
+        self.is_64_bit
+    }
+
+    /// Whether the `IMAGE_FILE_DLL` flag is set.
+    pub fn is_dll(&self) -> bool {
+        self.characteristics & 0x2000 != 0
+    }
+
+    /// The preferred base address of the image.
+    pub fn image_base(&self) -> u64 {
+        self.image_base
+    }
+
+    /// The RVA of the entry point, `0` if there is none.
+    pub fn entry_point(&self) -> u32 {
+        self.entry_point
+    }
+
+    pub fn size_of_image(&self) -> u32 {
+        self.size_of_image
+    }
+
+    pub fn size_of_headers(&self) -> u32 {
+        self.size_of_headers
+    }
+
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// Returns the given data directory, if it is present and non-empty.
+    pub fn data_directory(&self, kind: DataDirectoryKind) -> Option<DataDirectory> {
+        self.data_directories
+            .get(kind as usize)
+            .copied()
+            .filter(|dir| dir.rva != 0 && dir.size != 0)
+    }
+
+    /// Convert the given `rva` to an offset into [Self::data].
+    pub fn rva_to_offset(&self, rva: u32) -> Result<usize> {
+        if self.layout == Layout::Image || rva < self.size_of_headers {
+            return Ok(rva as usize);
+        }
+
+        self.sections
+            .iter()
+            .find(|section| section.contains(rva))
+            .ok_or(PeErrorKind::InvalidRva(rva))
+            .and_then(|section| add_rva(section.raw_offset, rva - section.virtual_address))
+            .map(|offset| offset as usize)
+    }
+
+    pub(crate) fn read_u16_at(&self, rva: u32) -> Result<u16> {
+        read_u16(self.data, self.rva_to_offset(rva)?)
+    }
+
+    pub(crate) fn read_u32_at(&self, rva: u32) -> Result<u32> {
+        read_u32(self.data, self.rva_to_offset(rva)?)
+    }
+
+    pub(crate) fn read_u64_at(&self, rva: u32) -> Result<u64> {
+        read_u64(self.data, self.rva_to_offset(rva)?)
+    }
+
+    /// Read a NUL terminated ASCII string at the given `rva`.
+    pub(crate) fn read_str_at(&self, rva: u32) -> Result<&'a str> {
+        let offset = self.rva_to_offset(rva)?;
+        let bytes = self
+            .data
+            .get(offset..)
+            .ok_or(PeErrorKind::OutOfBounds { offset, len: 1 })?;
+        let len = bytes
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(PeErrorKind::InvalidString(rva))?;
+
+        std::str::from_utf8(&bytes[..len]).map_err(|_| PeErrorKind::InvalidString(rva))
+    }
+}
+
+/// `rva + offset`, guarding against corrupt headers which would make it overflow.
+pub(crate) fn add_rva(rva: u32, offset: u32) -> Result<u32> {
+    rva.checked_add(offset).ok_or(PeErrorKind::OutOfBounds {
+        offset: rva as usize,
+        len: offset as usize,
+    })
+}
+
+/// `rva + index * entry_size`, the RVA of an entry of a table, guarding against corrupt headers which would make it
+/// overflow.
+pub(crate) fn table_rva(rva: u32, index: u32, entry_size: u32) -> Result<u32> {
+    index
+        .checked_mul(entry_size)
+        .and_then(|offset| rva.checked_add(offset))
+        .ok_or(PeErrorKind::OutOfBounds {
+            offset: rva as usize,
+            len: (index as usize).saturating_mul(entry_size as usize),
+        })
+}
+
+fn read_bytes(data: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(PeErrorKind::OutOfBounds { offset, len })
+}
+
+pub(crate) fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    Ok(u16::from_le_bytes(
+        read_bytes(data, offset, 2)?.try_into().unwrap(),
+    ))
+}
+
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    Ok(u32::from_le_bytes(
+        read_bytes(data, offset, 4)?.try_into().unwrap(),
+    ))
+}
+
+pub(crate) fn read_u64(data: &[u8], offset: usize) -> Result<u64> {
+    Ok(u64::from_le_bytes(
+        read_bytes(data, offset, 8)?.try_into().unwrap(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pe::testing::{FILE_OFFSET, SECTION_RVA, TestPe, put_u32};
+    use crate::pe::{Machine, PeErrorKind, PeFile};
+
+    #[test]
+    fn converts_rvas_to_file_offsets() {
+        let data = TestPe::new(Machine::X64).section(vec![0; 0x10]).build();
+        let pe = PeFile::parse(&data).unwrap();
+
+        assert_eq!(pe.rva_to_offset(0x40).unwrap(), 0x40);
+        assert_eq!(
+            pe.rva_to_offset(SECTION_RVA + 8).unwrap(),
+            FILE_OFFSET as usize + 8
+        );
+        assert!(matches!(
+            pe.rva_to_offset(SECTION_RVA + 0x1000),
+            Err(PeErrorKind::InvalidRva(_))
+        ));
+
+        let pe = PeFile::parse_image(&data).unwrap();
+        assert_eq!(
+            pe.rva_to_offset(SECTION_RVA + 8).unwrap(),
+            SECTION_RVA as usize + 8
+        );
+    }
+
+    #[test]
+    fn rejects_overflowing_raw_offset() {
+        let pe = TestPe::new(Machine::X64).section(vec![0; 0x10]);
+        let mut data = pe.build();
+        put_u32(&mut data, pe.section_header() + 20, u32::MAX - 4);
+
+        let pe = PeFile::parse(&data).unwrap();
+        assert!(matches!(
+            pe.rva_to_offset(SECTION_RVA + 8),
+            Err(PeErrorKind::OutOfBounds { .. })
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::pe::{DataDirectoryKind, PeErrorKind, PeFile, Result, add_rva, read_u32, read_u64};
+
+/// `IMAGE_REL_BASED_ABSOLUTE`, padding which is skipped.
+const REL_BASED_ABSOLUTE: u8 = 0;
+/// `IMAGE_REL_BASED_HIGHLOW`, a 32-bit address.
+const REL_BASED_HIGHLOW: u8 = 3;
+/// `IMAGE_REL_BASED_DIR64`, a 64-bit address.
+const REL_BASED_DIR64: u8 = 10;
+
+/// A single entry of the base relocation table, padding entries are omitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Relocation {
+    /// The RVA of the address which needs to be adjusted.
+    pub rva: u32,
+    /// The `IMAGE_REL_BASED_*` type.
+    pub kind: u8,
+}
+
+impl PeFile<'_> {
+    /// Parse the base relocation table.
+    ///
+    /// Returns an empty list if the file has no relocations, in which case it can only be loaded at its preferred
+    /// [image_base](Self::image_base).
+    pub fn relocations(&self) -> Result<Vec<Relocation>> {
+        let Some(directory) = self.data_directory(DataDirectoryKind::BaseRelocation) else {
+            return Ok(Vec::new());
+        };
+
+        let mut relocations = Vec::new();
+        let mut block = directory.rva;
+
+        while directory.contains(block) {
+            let page_rva = self.read_u32_at(block)?;
+            let block_size = self.read_u32_at(add_rva(block, 4)?)?;
+
+            if block_size < 8 {
+                break;
+            }
+
+            let block_end = add_rva(block, block_size)?;
+
+            for entry in (block + 8..block_end).step_by(2) {
+                let entry = self.read_u16_at(entry)?;
+                let kind = (entry >> 12) as u8;
+
+                if kind != REL_BASED_ABSOLUTE {
+                    relocations.push(Relocation {
+                        rva: add_rva(page_rva, (entry & 0xFFF) as u32)?,
+                        kind,
+                    });
+                }
+            }
+
+            block = block_end;
+        }
+
+        Ok(relocations)
+    }
+}
+
+/// Adjust all addresses given by `relocations` within a mapped `image` by `delta`, the difference between the
+/// actual and the preferred base address.
+pub fn apply_relocations(image: &mut [u8], relocations: &[Relocation], delta: u64) -> Result<()> {
+    for relocation in relocations {
+        let offset = relocation.rva as usize;
+
+        match relocation.kind {
+            REL_BASED_HIGHLOW => {
+                let value = read_u32(image, offset)?.wrapping_add(delta as u32);
+                image[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+            }
+            REL_BASED_DIR64 => {
+                let value = read_u64(image, offset)?.wrapping_add(delta);
+                image[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+            }
+            kind => return Err(PeErrorKind::UnsupportedRelocation(kind)),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pe::testing::{SECTION_RVA, TestPe, put_u16, put_u32, put_u64};
+    use crate::pe::{
+        DataDirectoryKind, Machine, PeErrorKind, PeFile, Relocation, apply_relocations,
+    };
+
+    fn relocation_fixture(block_size: u32) -> Vec<u8> {
+        let mut section = vec![0u8; 0x20];
+        put_u32(&mut section, 0, SECTION_RVA);
+        put_u32(&mut section, 4, block_size);
+        put_u16(&mut section, 8, 0x3010);
+        put_u16(&mut section, 10, 0xA018);
+        put_u16(&mut section, 12, 0x0000);
+        put_u16(&mut section, 14, 0x3020);
+
+        TestPe::new(Machine::X64)
+            .section(section)
+            .directory(DataDirectoryKind::BaseRelocation, SECTION_RVA, 0x10)
+            .build()
+    }
+
+    #[test]
+    fn parses_blocks_and_skips_padding() {
+        let data = relocation_fixture(0x10);
+        let pe = PeFile::parse(&data).unwrap();
+
+        assert_eq!(
+            pe.relocations().unwrap(),
+            vec![
+                Relocation {
+                    rva: 0x1010,
+                    kind: 3,
+                },
+                Relocation {
+                    rva: 0x1018,
+                    kind: 10,
+                },
+                Relocation {
+                    rva: 0x1020,
+                    kind: 3,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_overflowing_block_size() {
+        let data = relocation_fixture(0xFFFF_FFF0);
+        let pe = PeFile::parse(&data).unwrap();
+
+        assert!(matches!(
+            pe.relocations(),
+            Err(PeErrorKind::OutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn no_relocation_directory() {
+        let data = TestPe::new(Machine::X64).build();
+        let pe = PeFile::parse(&data).unwrap();
+
+        assert!(pe.relocations().unwrap().is_empty());
+    }
+
+    #[test]
+    fn applies_positive_and_negative_deltas() {
+        let mut image = vec![0u8; 0x20];
+        put_u32(&mut image, 0x08, 0x1000_1234);
+        put_u64(&mut image, 0x10, 0x1_8000_1000);
+
+        let highlow = [Relocation { rva: 0x08, kind: 3 }];
+        let dir64 = [Relocation {
+            rva: 0x10,
+            kind: 10,
+        }];
+
+        apply_relocations(&mut image, &highlow, 0x10).unwrap();
+        apply_relocations(&mut image, &dir64, (-0x1000i64) as u64).unwrap();
+        assert_eq!(image[0x08..0x0C], 0x1000_1244u32.to_le_bytes());
+        assert_eq!(image[0x10..0x18], 0x1_8000_0000u64.to_le_bytes());
+
+        // A negative delta wraps within 32 bits for `HIGHLOW`.
+        apply_relocations(&mut image, &highlow, (-0x44i64) as u64).unwrap();
+        assert_eq!(image[0x08..0x0C], 0x1000_1200u32.to_le_bytes());
+    }
+
+    #[test]
+    fn rejects_unsupported_and_out_of_bounds_relocations() {
+        let mut image = vec![0u8; 0x20];
+
+        assert!(matches!(
+            apply_relocations(&mut image, &[Relocation { rva: 0, kind: 5 }], 1),
+            Err(PeErrorKind::UnsupportedRelocation(5))
+        ));
+        assert!(matches!(
+            apply_relocations(&mut image, &[Relocation { rva: 0x1E, kind: 3 }], 1),
+            Err(PeErrorKind::OutOfBounds { .. })
+        ));
+        assert!(matches!(
+            apply_relocations(
+                &mut image,
+                &[Relocation {
+                    rva: 0x1C,
+                    kind: 10
+                }],
+                1
+            ),
+            Err(PeErrorKind::OutOfBounds { .. })
+        ));
+    }
+}
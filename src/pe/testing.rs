@@ -0,0 +1,188 @@
+//! Hand-made PE files for the unit tests, small enough to reason about byte by byte.
+//!
+//! Every file has its headers in the first `0x200` bytes and a single section holding all data, which is stored at
+//! [FILE_OFFSET] on disk and mapped at [SECTION_RVA].
+use crate::pe::{DataDirectoryKind, Machine};
+
+/// The RVA of the single section.
+pub const SECTION_RVA: u32 = 0x1000;
+/// The file offset of the single section.
+pub const FILE_OFFSET: u32 = 0x200;
+pub const IMAGE_BASE_32: u64 = 0x1000_0000;
+pub const IMAGE_BASE_64: u64 = 0x1_8000_0000;
+
+#[derive(Debug, Clone)]
+pub struct TestPe {
+    machine: Machine,
+    is_dll: bool,
+    directories: [(u32, u32); 16],
+    section: Vec<u8>,
+}
+
+impl TestPe {
+    /// A DLL for the given `machine`, `PE32` for x86 and `PE32+` for everything else.
+    pub fn new(machine: Machine) -> Self {
+        Self {
+            machine,
+            is_dll: true,
+            directories: [(0, 0); 16],
+            section: Vec::new(),
+        }
+    }
+
+    pub fn is_64_bit(&self) -> bool {
+        self.machine != Machine::X86
+    }
+
+    pub fn image_base(&self) -> u64 {
+        if self.is_64_bit() {
+            IMAGE_BASE_64
+        } else {
+            IMAGE_BASE_32
+        }
+    }
+
+    // Only used by the injection tests, which need Windows.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn dll(mut self, is_dll: bool) -> Self {
+        self.is_dll = is_dll;
+        self
+    }
+
+    /// The contents of the section, offset `0` is at [SECTION_RVA].
+    pub fn section(mut self, data: Vec<u8>) -> Self {
+        self.section = data;
+        self
+    }
+
+    pub fn directory(mut self, kind: DataDirectoryKind, rva: u32, size: u32) -> Self {
+        self.directories[kind as usize] = (rva, size);
+        self
+    }
+
+    /// The file offset of the header of the single section.
+    pub fn section_header(&self) -> usize {
+        if self.is_64_bit() {
+            0x58 + 240
+        } else {
+            0x58 + 224
+        }
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let raw_size = (self.section.len() as u32).next_multiple_of(0x200);
+        let virtual_size = (self.section.len() as u32).max(1);
+        let size_of_image = SECTION_RVA + virtual_size.next_multiple_of(0x1000);
+
+        let mut data = vec![0u8; (FILE_OFFSET + raw_size) as usize];
+        data[..2].copy_from_slice(b"MZ");
+        put_u32(&mut data, 0x3C, 0x40);
+        data[0x40..0x44].copy_from_slice(b"PE\0\0");
+
+        let (machine, optional_header_size, directories) = match self.machine {
+            Machine::X86 => (0x014C, 224, 96),
+            Machine::X64 => (0x8664, 240, 112),
+            Machine::Arm64 => (0xAA64, 240, 112),
+            Machine::Unknown(machine) => (machine, 240, 112),
+        };
+
+        let file_header = 0x44;
+        put_u16(&mut data, file_header, machine);
+        put_u16(&mut data, file_header + 2, 1);
+        put_u16(&mut data, file_header + 16, optional_header_size);
+        // `EXECUTABLE_IMAGE`, and `DLL` if requested.
+        put_u16(
+            &mut data,
+            file_header + 18,
+            0x0002 | if self.is_dll { 0x2000 } else { 0 },
+        );
+
+        let optional = file_header + 20;
+        if self.is_64_bit() {
+            put_u16(&mut data, optional, 0x20B);
+            put_u64(&mut data, optional + 24, self.image_base());
+        } else {
+            put_u16(&mut data, optional, 0x10B);
+            put_u32(&mut data, optional + 28, self.image_base() as u32);
+        }
+        put_u32(&mut data, optional + 32, 0x1000);
+        put_u32(&mut data, optional + 36, 0x200);
+        put_u32(&mut data, optional + 56, size_of_image);
+        put_u32(&mut data, optional + 60, FILE_OFFSET);
+        put_u32(&mut data, optional + directories - 4, 16);
+
+        for (i, (rva, size)) in self.directories.iter().enumerate() {
+            put_u32(&mut data, optional + directories + i * 8, *rva);
+            put_u32(&mut data, optional + directories + i * 8 + 4, *size);
+        }
+
+        let section = self.section_header();
+        data[section..section + 5].copy_from_slice(b".data");
+        put_u32(&mut data, section + 8, virtual_size);
+        put_u32(&mut data, section + 12, SECTION_RVA);
+        put_u32(&mut data, section + 16, raw_size);
+        put_u32(&mut data, section + 20, FILE_OFFSET);
+        // Readable, writable and executable.
+        put_u32(&mut data, section + 36, 0xE000_0020);
+
+        let start = FILE_OFFSET as usize;
+        data[start..start + self.section.len()].copy_from_slice(&self.section);
+
+        data
+    }
+}
+
+pub fn put_u16(data: &mut [u8], offset: usize, value: u16) {
+    data[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+pub fn put_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+pub fn put_u64(data: &mut [u8], offset: usize, value: u64) {
+    data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Write a NUL terminated string.
+pub fn put_str(data: &mut [u8], offset: usize, value: &str) {
+    data[offset..offset + value.len()].copy_from_slice(value.as_bytes());
+    data[offset + value.len()] = 0;
+}
+
+/// A DLL named `test.dll` exporting, starting at ordinal `5`:
+/// * `Alpha` at RVA `0x2000`.
+/// * An unused slot.
+/// * `Forwarded`, forwarded to `NTDLL.RtlAllocateHeap`.
+/// * An ordinal-only export at RVA `0x2010`.
+pub fn export_fixture() -> TestPe {
+    let mut section = vec![0u8; 0x100];
+    let rva = |offset: u32| SECTION_RVA + offset;
+
+    put_u32(&mut section, 12, rva(0x80));
+    put_u32(&mut section, 16, 5);
+    put_u32(&mut section, 20, 4);
+    put_u32(&mut section, 24, 2);
+    put_u32(&mut section, 28, rva(0x40));
+    put_u32(&mut section, 32, rva(0x60));
+    put_u32(&mut section, 36, rva(0x70));
+
+    for (i, function) in [0x2000, 0, rva(0xB0), 0x2010].into_iter().enumerate() {
+        put_u32(&mut section, 0x40 + i * 4, function);
+    }
+    put_u32(&mut section, 0x60, rva(0x90));
+    put_u32(&mut section, 0x64, rva(0x98));
+    put_u16(&mut section, 0x70, 0);
+    put_u16(&mut section, 0x72, 2);
+
+    put_str(&mut section, 0x80, "test.dll");
+    put_str(&mut section, 0x90, "Alpha");
+    put_str(&mut section, 0x98, "Forwarded");
+    put_str(&mut section, 0xB0, "NTDLL.RtlAllocateHeap");
+
+    TestPe::new(Machine::X64).section(section).directory(
+        DataDirectoryKind::Export,
+        SECTION_RVA,
+        0x100,
+    )
+}
@@ -0,0 +1,186 @@
+//! Build-time generation of proxy DLL exports from a reference DLL.
+//!
+//! Reads the export table of an existing DLL and emits either a module-definition (`.def`) file which forwards every
+//! export to the original DLL, or Rust source code for [lazy_proxy_dll!](crate::lazy_proxy_dll) which jumps through
+//! a lazily resolved pointer table.
+//! Neither approach requires knowledge of the argument types of the proxied functions.
+//!
+//! This module has no Windows dependencies, and is meant to be used from a `build.rs` script.
+//!
+//! # Example
+//! ```ignore
+//! // build.rs
+//! let generator = rust_hooking_utils::proxy_gen::ProxyGenerator::from_file(r"C:\Windows\System32\winmm.dll")?;
+//! let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+//! std::fs::write(out_dir.join("winmm_proxy.rs"), generator.rust_stubs())?;
+//!
+//! // lib.rs
+//! include!(concat!(env!("OUT_DIR"), "/winmm_proxy.rs"));
+//! ```
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::pe::{Export, PeFile};
+
+/// The directory forwarders point to by default.
+pub const SYSTEM_DIRECTORY: &str = r"C:\Windows\System32";
+
+#[derive(Debug, Clone)]
+pub struct ProxyGenerator {
+    dll_name: String,
+    exports: Vec<Export>,
+}
+
+impl ProxyGenerator {
+    /// Read the exports of the DLL at `path`, the file name is used as the name of the proxied DLL.
+    pub fn from_file(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let dll_name = path
+            .file_name()
+            .ok_or_else(|| eyre::eyre!("Path has no file name: {:?}", path))?
+            .to_string_lossy()
+            .into_owned();
+        let data = std::fs::read(path)?;
+
+        Ok(Self::from_bytes(dll_name, &data)?)
+    }
+
+    /// Read the exports of the DLL file contained in `data`.
+    pub fn from_bytes(dll_name: impl Into<String>, data: &[u8]) -> crate::pe::Result<Self> {
+        let exports = PeFile::parse(data)?.exports()?;
+
+        Ok(Self {
+            dll_name: dll_name.into(),
+            exports,
+        })
+    }
+
+    /// The file name of the proxied DLL, e.g. `winmm.dll`.
+    pub fn dll_name(&self) -> &str {
+        &self.dll_name
+    }
+
+    /// All exports of the reference DLL, sorted by ordinal.
+    pub fn exports(&self) -> &[Export] {
+        &self.exports
+    }
+
+    /// Generate a `.def` file which forwards every export to the original DLL in `original_dir`.
+    ///
+    /// Named exports keep their original ordinal, ordinal-only exports are forwarded as `NONAME` exports.
+    /// Pass it to the linker with `cargo:rustc-cdylib-link-arg=/DEF:<path>`.
+    pub fn def_file(&self, original_dir: &str) -> String {
+        let module = self.forward_module(original_dir);
+        let mut out = String::from("EXPORTS\n");
+
+        for export in &self.exports {
+            let _ = match &export.name {
+                Some(name) => writeln!(out, "    {name}={module}.{name} @{}", export.ordinal),
+                None => writeln!(
+                    out,
+                    "    __proxy_ordinal_{0}={module}.#{0} @{0} NONAME",
+                    export.ordinal
+                ),
+            };
+        }
+
+        out
+    }
+
+    /// Generate `/EXPORT` linker arguments which forward every named export to the original DLL in `original_dir`.
+    ///
+    /// An alternative to [Self::def_file] which doesn't require writing a file, each argument can be passed with
+    /// `cargo:rustc-cdylib-link-arg=<arg>`.
+    pub fn export_link_args(&self, original_dir: &str) -> Vec<String> {
+        let module = self.forward_module(original_dir);
+
+        self.named_exports()
+            .map(|(name, export)| format!("/EXPORT:{name}={module}.{name},@{}", export.ordinal))
+            .collect()
+    }
+
+    /// Generate Rust source code invoking [lazy_proxy_dll!](crate::lazy_proxy_dll) for every named export.
+    ///
+    /// Ordinal-only exports are skipped, as Rust can't export symbols by ordinal. Exported variables can't be
+    /// proxied this way either, and will be turned into (nonsensical) functions.
+    pub fn rust_stubs(&self) -> String {
+        let mut out = format!(
+            "// Generated by `rust_hooking_utils::proxy_gen` from `{}`, do not edit.\n",
+            self.dll_name
+        );
+
+        for export in self.exports.iter().filter(|e| e.name.is_none()) {
+            let _ = writeln!(out, "// Skipped ordinal-only export #{}", export.ordinal);
+        }
+
+        let _ = writeln!(out, "::rust_hooking_utils::lazy_proxy_dll! {{");
+        let _ = writeln!(out, "    {:?};", self.dll_name);
+
+        for (index, (name, _)) in self.named_exports().enumerate() {
+            let _ = writeln!(out, "    {index} => {name:?} as __proxy_export_{index},");
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    fn named_exports(&self) -> impl Iterator<Item = (&str, &Export)> {
+        self.exports
+            .iter()
+            .filter_map(|e| e.name.as_deref().map(|name| (name, e)))
+    }
+
+    /// The forwarder module path, e.g. `C:\Windows\System32\winmm`.
+    fn forward_module(&self, original_dir: &str) -> String {
+        let stem = self
+            .dll_name
+            .rsplit_once('.')
+            .map_or(self.dll_name.as_str(), |(stem, _)| stem);
+
+        format!("{}\\{stem}", original_dir.trim_end_matches(['\\', '/']))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProxyGenerator;
+    use crate::pe::testing::export_fixture;
+
+    fn generator() -> ProxyGenerator {
+        ProxyGenerator::from_bytes("test.dll", &export_fixture().build()).unwrap()
+    }
+
+    #[test]
+    fn def_file_keeps_ordinals() {
+        assert_eq!(
+            generator().def_file(r"C:\Windows\System32\"),
+            concat!(
+                "EXPORTS\n",
+                "    Alpha=C:\\Windows\\System32\\test.Alpha @5\n",
+                "    Forwarded=C:\\Windows\\System32\\test.Forwarded @7\n",
+                "    __proxy_ordinal_8=C:\\Windows\\System32\\test.#8 @8 NONAME\n",
+            )
+        );
+    }
+
+    #[test]
+    fn link_args_skip_ordinal_only_exports() {
+        assert_eq!(
+            generator().export_link_args("D:/Original"),
+            vec![
+                r"/EXPORT:Alpha=D:/Original\test.Alpha,@5",
+                r"/EXPORT:Forwarded=D:/Original\test.Forwarded,@7",
+            ]
+        );
+    }
+
+    #[test]
+    fn rust_stubs_index_named_exports() {
+        let stubs = generator().rust_stubs();
+
+        assert!(stubs.contains("// Skipped ordinal-only export #8\n"));
+        assert!(stubs.contains("    \"test.dll\";\n"));
+        assert!(stubs.contains("    0 => \"Alpha\" as __proxy_export_0,\n"));
+        assert!(stubs.contains("    1 => \"Forwarded\" as __proxy_export_1,\n"));
+    }
+}
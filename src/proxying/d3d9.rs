@@ -0,0 +1,24 @@
+//! Contains a pre-made proxy for d3d9.dll
+//!
+//! Enabling the `proxy-d3d9` feature will automatically export the pre-requisite functions if the parent crate is
+//! compiled as a `crate-type = ["cdylib"]`.
+
+use std::ffi::c_void;
+
+use windows::Win32::Foundation::E_FAIL;
+use windows::core::{BOOL, HRESULT, PCWSTR};
+
+crate::proxy_dll! {
+    pub struct Direct3D9ProxyManager("d3d9.dll");
+    pub static D3D9_MANAGER;
+
+    fn Direct3DCreate9(sdk_version: u32) -> *mut c_void = std::ptr::null_mut();
+    fn Direct3DCreate9Ex(sdk_version: u32, d3d: *mut *mut c_void) -> HRESULT = E_FAIL;
+    fn D3DPERF_BeginEvent(colour: u32, name: PCWSTR) -> i32 = -1;
+    fn D3DPERF_EndEvent() -> i32 = -1;
+    fn D3DPERF_GetStatus() -> u32 = 0;
+    fn D3DPERF_QueryRepeatFrame() -> BOOL = BOOL(0);
+    fn D3DPERF_SetMarker(colour: u32, name: PCWSTR) = ();
+    fn D3DPERF_SetOptions(options: u32) = ();
+    fn D3DPERF_SetRegion(colour: u32, name: PCWSTR) = ();
+}
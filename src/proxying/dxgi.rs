@@ -0,0 +1,21 @@
+//! Contains a pre-made proxy for dxgi.dll
+//!
+//! Enabling the `proxy-dxgi` feature will automatically export the pre-requisite functions if the parent crate is
+//! compiled as a `crate-type = ["cdylib"]`.
+//!
+//! Only the factory and debug interface entry points are forwarded, as these are the only exports games link against.
+
+use std::ffi::c_void;
+
+use windows::Win32::Foundation::E_FAIL;
+use windows::core::{GUID, HRESULT};
+
+crate::proxy_dll! {
+    pub struct DxgiProxyManager("dxgi.dll");
+    pub static DXGI_MANAGER;
+
+    fn CreateDXGIFactory(riid: *const GUID, factory: *mut *mut c_void) -> HRESULT = E_FAIL;
+    fn CreateDXGIFactory1(riid: *const GUID, factory: *mut *mut c_void) -> HRESULT = E_FAIL;
+    fn CreateDXGIFactory2(flags: u32, riid: *const GUID, factory: *mut *mut c_void) -> HRESULT = E_FAIL;
+    fn DXGIGetDebugInterface1(flags: u32, riid: *const GUID, debug: *mut *mut c_void) -> HRESULT = E_FAIL;
+}
@@ -0,0 +1,275 @@
+/// Generate a lazily loaded proxy for a system DLL.
+///
+/// Creates a manager struct which loads the original DLL and resolves all listed functions, a [once_cell::sync::Lazy]
+/// static holding said manager (or the [ProxyErrorKind](crate::proxying::locate::ProxyErrorKind) describing why it
+/// couldn't be created), and a `#[no_mangle]` export for every listed function which forwards the call to the
+/// original DLL. See [locate](crate::proxying::locate) for where the original DLL is loaded from.
+///
+/// If the original DLL is unavailable an export returns its `= fallback` value, exports without a fallback log the
+/// error and abort the process.
+///
+/// The exports will only be visible to the outside world if the parent crate is compiled as a `crate-type = ["cdylib"]`.
+///
+/// # Example
+/// ```ignore
+/// use std::ffi::c_void;
+/// use windows::core::HRESULT;
+/// use windows::Win32::Foundation::E_FAIL;
+///
+/// rust_hooking_utils::proxy_dll! {
+///     pub struct DxgiProxyManager("dxgi.dll");
+///     pub static DXGI_MANAGER;
+///
+///     fn CreateDXGIFactory(riid: *const windows::core::GUID, pp_factory: *mut *mut c_void) -> HRESULT = E_FAIL;
+///     fn CreateDXGIFactory1(riid: *const windows::core::GUID, pp_factory: *mut *mut c_void) -> HRESULT;
+/// }
+/// ```
+#[macro_export]
+macro_rules! proxy_dll {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $manager:ident($dll:literal);
+        $static_vis:vis static $static_name:ident;
+
+        $(
+            $(#[$fn_meta:meta])*
+            fn $name:ident($($arg:ident: $arg_ty:ty),* $(,)?) $(-> $ret:ty)? $(= $fallback:expr)?;
+        )*
+    ) => {
+        $(#[$meta])*
+        #[allow(non_snake_case)]
+        $vis struct $manager {
+            _lib: $crate::proxying::__private::libloading::Library,
+            $(
+                $name: $crate::proxying::__private::Symbol<unsafe extern "system" fn($($arg_ty),*) $(-> $ret)?>,
+            )*
+        }
+
+        unsafe impl Sync for $manager {}
+
+        impl $manager {
+            /// The file name of the proxied DLL.
+            pub const DLL_NAME: &'static str = $dll;
+            /// The names of all functions which are forwarded to the original DLL, in declaration order.
+            pub const EXPORTS: &'static [&'static str] = &[$(stringify!($name)),*];
+
+            pub fn new() -> $crate::proxying::locate::Result<Self> {
+                let lib = $crate::proxying::locate::load_original_dll($dll)?;
+
+                unsafe {
+                    // Couldn't manage to make the lifetimes work, so into raw they go!
+                    Ok(Self {
+                        $(
+                            $name: lib
+                                .get::<unsafe extern "system" fn($($arg_ty),*) $(-> $ret)?>(stringify!($name).as_bytes())
+                                .map_err(|source| $crate::proxying::locate::ProxyErrorKind::MissingExport {
+                                    dll_name: $dll,
+                                    export: stringify!($name),
+                                    source,
+                                })?
+                                .into_raw(),
+                        )*
+                        _lib: lib,
+                    })
+                }
+            }
+        }
+
+        $static_vis static $static_name: $crate::proxying::__private::Lazy<
+            $crate::proxying::locate::Result<$manager>,
+        > = $crate::proxying::__private::Lazy::new(|| {
+            $manager::new().inspect_err(|e| {
+                $crate::proxying::__private::log::error!(concat!("Failed to initialize ", $dll, ": {:?}"), e)
+            })
+        });
+
+        $(
+            $(#[$fn_meta])*
+            #[allow(clippy::missing_safety_doc)]
+            #[unsafe(no_mangle)]
+            pub unsafe extern "system" fn $name($($arg: $arg_ty),*) $(-> $ret)? {
+                $crate::proxying::__private::log::trace!(concat!(stringify!($name), " called"));
+                match &*$static_name {
+                    Ok(manager) => unsafe { (manager.$name)($($arg),*) },
+                    Err(_) => $crate::__proxy_fallback!($dll, $name $(, $fallback)?),
+                }
+            }
+        )*
+    };
+}
+
+/// The value returned by a [proxy_dll!](crate::proxy_dll) export when the original DLL is unavailable.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __proxy_fallback {
+    ($dll:literal, $name:ident) => {
+        $crate::proxying::__private::unavailable($dll, stringify!($name))
+    };
+    ($dll:literal, $name:ident, $fallback:expr) => {
+        $fallback
+    };
+}
+
+/// Generate a proxy which forwards exports to the original system DLL without knowing their signatures.
+///
+/// Every export is a small assembly stub which jumps through a pointer table, resolving its entry on first use.
+/// As the stubs never touch the arguments any calling convention is supported. Usually this macro isn't written by
+/// hand, but generated from a reference DLL with [ProxyGenerator::rust_stubs](crate::proxy_gen::ProxyGenerator::rust_stubs).
+///
+/// Only supported on `x86` and `x86_64`.
+///
+/// # Example
+/// ```ignore
+/// rust_hooking_utils::lazy_proxy_dll! {
+///     "winmm.dll";
+///     0 => "timeGetTime" as __proxy_export_0,
+///     1 => "timeBeginPeriod" as __proxy_export_1,
+/// }
+/// ```
+#[macro_export]
+macro_rules! lazy_proxy_dll {
+    (
+        $dll:literal;
+        $($index:literal => $export:literal as $stub:ident),* $(,)?
+    ) => {
+        const _: () = {
+            const EXPORT_COUNT: usize = [$($export),*].len();
+
+            static SLOTS: [$crate::proxying::__private::AtomicUsize; EXPORT_COUNT] =
+                [const { $crate::proxying::__private::AtomicUsize::new(0) }; EXPORT_COUNT];
+
+            static PROXY: $crate::proxying::stubs::LazyProxy =
+                $crate::proxying::stubs::LazyProxy::new($dll, &[$($export),*], &SLOTS);
+
+            unsafe extern "C" fn resolve(index: usize) -> usize {
+                PROXY.resolve(index)
+            }
+
+            $(
+                $crate::__proxy_stub!($stub, $export, $index, SLOTS, resolve);
+            )*
+        };
+    };
+}
+
+/// A single naked export stub for [lazy_proxy_dll!](crate::lazy_proxy_dll).
+///
+/// Jumps to the slot at `$index` if it is resolved, otherwise preserves all argument registers and calls `$resolve`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __proxy_stub {
+    ($stub:ident, $export:literal, $index:literal, $slots:ident, $resolve:ident) => {
+        #[cfg(target_arch = "x86_64")]
+        #[unsafe(naked)]
+        #[unsafe(export_name = $export)]
+        unsafe extern "system" fn $stub() {
+            ::core::arch::naked_asm!(
+                "mov rax, qword ptr [rip + {slots} + {offset}]",
+                "test rax, rax",
+                "jz 2f",
+                "jmp rax",
+                "2:",
+                "push rcx",
+                "push rdx",
+                "push r8",
+                "push r9",
+                // Shadow space + xmm0-3, keeps the stack 16 byte aligned.
+                "sub rsp, 0x68",
+                "movdqu xmmword ptr [rsp + 0x20], xmm0",
+                "movdqu xmmword ptr [rsp + 0x30], xmm1",
+                "movdqu xmmword ptr [rsp + 0x40], xmm2",
+                "movdqu xmmword ptr [rsp + 0x50], xmm3",
+                "mov rcx, {index}",
+                "call {resolve}",
+                "movdqu xmm0, xmmword ptr [rsp + 0x20]",
+                "movdqu xmm1, xmmword ptr [rsp + 0x30]",
+                "movdqu xmm2, xmmword ptr [rsp + 0x40]",
+                "movdqu xmm3, xmmword ptr [rsp + 0x50]",
+                "add rsp, 0x68",
+                "pop r9",
+                "pop r8",
+                "pop rdx",
+                "pop rcx",
+                "jmp rax",
+                slots = sym $slots,
+                offset = const $index * 8,
+                index = const $index,
+                resolve = sym $resolve,
+            )
+        }
+
+        #[cfg(target_arch = "x86")]
+        #[unsafe(naked)]
+        #[unsafe(export_name = $export)]
+        unsafe extern "system" fn $stub() {
+            ::core::arch::naked_asm!(
+                "mov eax, dword ptr [{slots} + {offset}]",
+                "test eax, eax",
+                "jz 2f",
+                "jmp eax",
+                "2:",
+                // `thiscall` and `fastcall` pass arguments in these.
+                "push ecx",
+                "push edx",
+                "push {index}",
+                "call {resolve}",
+                "add esp, 4",
+                "pop edx",
+                "pop ecx",
+                "jmp eax",
+                slots = sym $slots,
+                offset = const $index * 4,
+                index = const $index,
+                resolve = sym $resolve,
+            )
+        }
+
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+        compile_error!("`lazy_proxy_dll!` is only supported on x86 and x86_64");
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    // A DLL which doesn't exist anywhere, so every export has to take its fallback path.
+    crate::proxy_dll! {
+        struct MissingProxyManager("rust_hooking_utils_missing.dll");
+        static MISSING_MANAGER;
+
+        fn RhuTestFirst(value: u32) -> u32 = 7;
+        fn RhuTestSecond(left: i32, right: i32) -> i32 = -1;
+        fn RhuTestNoFallback();
+    }
+
+    #[test]
+    fn symbol_table_lists_exports_in_declaration_order() {
+        assert_eq!(
+            MissingProxyManager::DLL_NAME,
+            "rust_hooking_utils_missing.dll"
+        );
+        assert_eq!(
+            MissingProxyManager::EXPORTS,
+            &["RhuTestFirst", "RhuTestSecond", "RhuTestNoFallback"]
+        );
+    }
+
+    #[test]
+    fn exports_have_the_declared_signatures() {
+        let _: unsafe extern "system" fn(u32) -> u32 = RhuTestFirst;
+        let _: unsafe extern "system" fn(i32, i32) -> i32 = RhuTestSecond;
+        let _: unsafe extern "system" fn() = RhuTestNoFallback;
+    }
+
+    #[test]
+    fn missing_dll_returns_fallbacks() {
+        assert!(matches!(
+            &*MISSING_MANAGER,
+            Err(crate::proxying::locate::ProxyErrorKind::NotFound { .. })
+        ));
+
+        unsafe {
+            assert_eq!(RhuTestFirst(1), 7);
+            assert_eq!(RhuTestSecond(1, 2), -1);
+        }
+    }
+}
@@ -0,0 +1,500 @@
+//! Runtime support for [dll_main!](crate::dll_main), and unloading the DLL from within with [eject].
+use std::ffi::c_void;
+use std::panic::AssertUnwindSafe;
+#[cfg(feature = "patching")]
+use std::sync::Arc;
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use windows::Win32::Foundation::HMODULE;
+
+#[cfg(feature = "patching")]
+use crate::patching::LocalPatcher;
+use crate::reporting::{report_error, report_panic, report_warning};
+use windows::Win32::System::LibraryLoader::{DisableThreadLibraryCalls, FreeLibraryAndExitThread};
+use windows::Win32::System::SystemServices::{
+    DLL_PROCESS_ATTACH, DLL_PROCESS_DETACH, DLL_THREAD_ATTACH, DLL_THREAD_DETACH,
+};
+
+/// Where the `attach` function of [dll_main!](crate::dll_main) is run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AttachMode {
+    /// On a newly spawned thread, the default.
+    ///
+    /// The thread only starts running once `DllMain` returns, so the loader lock is no longer held.
+    #[default]
+    Thread,
+    /// Directly inside `DllMain`, while holding the loader lock.
+    ///
+    /// Guarantees the `attach` function has finished before the game's `main` runs, which is required for some hooks.
+    /// Loading libraries, spawning and waiting on threads, or anything else which requires the loader lock will
+    /// deadlock in this mode.
+    Sync,
+}
+
+/// The default time `DLL_PROCESS_DETACH` waits for a still running `attach` function.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Information passed to all [dll_main!](crate::dll_main) handlers.
+#[derive(Debug, Clone, Copy)]
+pub struct DllContext {
+    /// The module of this DLL.
+    pub module: HMODULE,
+    pub attach_mode: AttachMode,
+    /// Signalled once the DLL is being unloaded, long-running `attach` functions should poll it and return early.
+    pub shutdown: ShutdownToken,
+}
+
+// SAFETY: A `HMODULE` is valid process wide.
+unsafe impl Send for DllContext {}
+unsafe impl Sync for DllContext {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttachProgress {
+    NotStarted,
+    Running,
+    Finished,
+}
+
+#[derive(Debug)]
+struct ShutdownState {
+    requested: bool,
+    attach: AttachProgress,
+}
+
+/// Coordinates the shutdown between the `attach` function and `DLL_PROCESS_DETACH`.
+#[derive(Debug)]
+pub struct ShutdownSignal {
+    state: Mutex<ShutdownState>,
+    changed: Condvar,
+}
+
+impl ShutdownSignal {
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(ShutdownState {
+                requested: false,
+                attach: AttachProgress::NotStarted,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ShutdownState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Mark `attach` as running, returns `false` if a shutdown was requested before it could start.
+    fn start_attach(&self) -> bool {
+        let mut state = self.lock();
+        state.attach = AttachProgress::Running;
+        !state.requested
+    }
+
+    fn finish_attach(&self) {
+        self.lock().attach = AttachProgress::Finished;
+        self.changed.notify_all();
+    }
+
+    /// Request a shutdown and wait up to `timeout` for a running `attach` to finish.
+    ///
+    /// Returns `false` if `attach` was still running after the timeout. If `attach` hasn't started yet there is
+    /// nothing to wait for, it will see the request and never run.
+    fn request_and_wait(&self, timeout: Duration) -> bool {
+        let mut state = self.lock();
+        state.requested = true;
+        self.changed.notify_all();
+
+        let (state, _) = self
+            .changed
+            .wait_timeout_while(state, timeout, |state| {
+                state.attach == AttachProgress::Running
+            })
+            .unwrap_or_else(|e| e.into_inner());
+
+        state.attach != AttachProgress::Running
+    }
+}
+
+impl Default for ShutdownSignal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A handle to the [ShutdownSignal] of a [dll_main!](crate::dll_main) invocation.
+#[derive(Debug, Clone, Copy)]
+pub struct ShutdownToken(&'static ShutdownSignal);
+
+impl ShutdownToken {
+    pub fn new(signal: &'static ShutdownSignal) -> Self {
+        Self(signal)
+    }
+
+    /// Whether a shutdown was requested.
+    pub fn is_requested(&self) -> bool {
+        self.0.lock().requested
+    }
+
+    /// Request a shutdown, without unloading the DLL.
+    pub fn request(&self) {
+        self.0.lock().requested = true;
+        self.0.changed.notify_all();
+    }
+
+    /// Sleep for up to `timeout`, waking up early if a shutdown is requested.
+    ///
+    /// Returns whether a shutdown was requested, making it suitable for worker loops:
+    /// ```norun
+    /// while !context.shutdown.wait_timeout(Duration::from_millis(100)) {
+    ///     // Do work
+    /// }
+    /// ```
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let state = self.0.lock();
+        let (state, _) = self
+            .0
+            .changed
+            .wait_timeout_while(state, timeout, |state| !state.requested)
+            .unwrap_or_else(|e| e.into_inner());
+
+        state.requested
+    }
+}
+
+/// The handlers of a [dll_main!](crate::dll_main) invocation, `S` is the state created by `attach`.
+pub struct DllHandlers<S> {
+    pub attach: fn(&DllContext) -> eyre::Result<S>,
+    pub detach: Option<fn(&DllContext, S) -> eyre::Result<()>>,
+    pub thread_attach: Option<fn(&DllContext)>,
+    pub thread_detach: Option<fn(&DllContext)>,
+    pub attach_mode: AttachMode,
+    /// How long `DLL_PROCESS_DETACH` waits for a still running `attach` before giving up.
+    pub shutdown_timeout: Duration,
+}
+
+/// Dispatches `DllMain` calls to the [DllHandlers], and holds the state in between.
+pub struct DllLifecycle<S> {
+    handlers: DllHandlers<S>,
+    state: Mutex<Option<S>>,
+    shutdown: ShutdownSignal,
+    attach_thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl<S: Send + 'static> DllLifecycle<S> {
+    pub const fn new(handlers: DllHandlers<S>) -> Self {
+        Self {
+            handlers,
+            state: Mutex::new(None),
+            shutdown: ShutdownSignal::new(),
+            attach_thread: Mutex::new(None),
+        }
+    }
+
+    /// Handle a single `DllMain` call, always returns `true`.
+    ///
+    /// Errors and panics in the handlers are reported, but never fail the loading of the DLL.
+    pub fn dll_main(&'static self, module: HMODULE, reason: u32, reserved: *const c_void) -> bool {
+        let context = self.context(module);
+
+        match reason {
+            DLL_PROCESS_ATTACH => {
+                let _ = REGISTERED_LIFECYCLE.set(self);
+
+                if self.handlers.thread_attach.is_none() && self.handlers.thread_detach.is_none() {
+                    let _ = unsafe { DisableThreadLibraryCalls(module) };
+                }
+
+                match self.handlers.attach_mode {
+                    AttachMode::Thread => {
+                        match std::panic::catch_unwind(AssertUnwindSafe(|| {
+                            std::thread::spawn(move || self.attach(&context))
+                        })) {
+                            Ok(handle) => *lock(&self.attach_thread) = Some(handle),
+                            Err(e) => report_panic("dll_attach thread spawn", &*e),
+                        }
+                    }
+                    AttachMode::Sync => self.attach(&context),
+                }
+            }
+            // `reserved` is null if we're being unloaded by `FreeLibrary`, then we're still in a consistent state and
+            // can clean up safely. Otherwise the process is terminating and all other threads are already gone.
+            DLL_PROCESS_DETACH if reserved.is_null() => {
+                // Whatever happens below, our module is unmapped once we return, so the detours must not point into
+                // it anymore.
+                disable_hooks_and_patches();
+
+                // We only wait for the signal of `attach`, never for its thread to exit, as that requires the loader
+                // lock we're currently holding.
+                if !self
+                    .shutdown
+                    .request_and_wait(self.handlers.shutdown_timeout)
+                {
+                    report_warning(format_args!(
+                        "`dll_attach` is still running after {:?}, skipping `dll_detach`",
+                        self.handlers.shutdown_timeout
+                    ));
+                    return true;
+                }
+
+                self.detach(&context);
+            }
+            DLL_THREAD_ATTACH => Self::run_thread_handler(self.handlers.thread_attach, &context),
+            DLL_THREAD_DETACH => Self::run_thread_handler(self.handlers.thread_detach, &context),
+            _ => {}
+        }
+
+        true
+    }
+
+    fn context(&'static self, module: HMODULE) -> DllContext {
+        DllContext {
+            module,
+            attach_mode: self.handlers.attach_mode,
+            shutdown: ShutdownToken(&self.shutdown),
+        }
+    }
+
+    fn detach(&self, context: &DllContext) {
+        let state = lock(&self.state).take();
+
+        if let (Some(detach), Some(state)) = (self.handlers.detach, state) {
+            match std::panic::catch_unwind(AssertUnwindSafe(|| detach(context, state))) {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => report_error("dll_detach", &e),
+                Err(e) => report_panic("dll_detach", &*e),
+            }
+        }
+    }
+
+    fn attach(&self, context: &DllContext) {
+        if !self.shutdown.start_attach() {
+            self.shutdown.finish_attach();
+            return;
+        }
+
+        match std::panic::catch_unwind(AssertUnwindSafe(|| (self.handlers.attach)(context))) {
+            Ok(Ok(state)) => *lock(&self.state) = Some(state),
+            Ok(Err(e)) => report_error("dll_attach", &e),
+            Err(e) => report_panic("dll_attach", &*e),
+        }
+
+        // Only signal once the state is stored, so `detach` is guaranteed to see it.
+        self.shutdown.finish_attach();
+    }
+
+    fn run_thread_handler(handler: Option<fn(&DllContext)>, context: &DllContext) {
+        if let Some(handler) = handler
+            && let Err(e) = std::panic::catch_unwind(|| handler(context))
+        {
+            report_panic("thread handler", &*e);
+        }
+    }
+}
+
+/// The lifecycle of the [dll_main!](crate::dll_main) invocation in this DLL, if any.
+static REGISTERED_LIFECYCLE: OnceLock<&'static dyn EjectDetach> = OnceLock::new();
+
+trait EjectDetach: Sync {
+    /// Run the `detach` handler outside of `DllMain`, so it is skipped once `DLL_PROCESS_DETACH` arrives.
+    ///
+    /// Returns `false` if the attach thread is still running, then our module must stay loaded.
+    fn eject_detach(&'static self, module: HMODULE) -> bool;
+}
+
+impl<S: Send + 'static> EjectDetach for DllLifecycle<S> {
+    fn eject_detach(&'static self, module: HMODULE) -> bool {
+        let timeout = self.handlers.shutdown_timeout;
+
+        if !self.shutdown.request_and_wait(timeout) {
+            report_warning(format_args!(
+                "`dll_attach` is still running after {:?}, skipping `dll_detach` and leaving the DLL loaded",
+                timeout
+            ));
+            return false;
+        }
+
+        // Unlike in `DllMain` we aren't holding the loader lock, so we can wait for the attach thread to fully exit.
+        // Otherwise it might still be executing code in our module when it is unmapped.
+        let mut attach_finished = true;
+        if let Some(handle) = lock(&self.attach_thread).take() {
+            let deadline = Instant::now() + timeout;
+            while !handle.is_finished() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            attach_finished = handle.is_finished();
+        }
+
+        self.detach(&self.context(module));
+
+        if !attach_finished {
+            report_warning(format_args!(
+                "The `dll_attach` thread has not exited after {:?}, leaving the DLL loaded",
+                timeout
+            ));
+        }
+
+        attach_finished
+    }
+}
+
+/// A hook which can be disabled by [eject], and listed and toggled from the debug console, see [register_hook].
+pub trait ToggleableHook: Send + Sync {
+    fn is_enabled(&self) -> bool;
+
+    /// # Safety
+    ///
+    /// See [retour::StaticDetour::enable].
+    unsafe fn set_enabled(&self, enabled: bool) -> eyre::Result<()>;
+}
+
+impl<T: retour::Function> ToggleableHook for &'static retour::StaticDetour<T> {
+    fn is_enabled(&self) -> bool {
+        retour::StaticDetour::is_enabled(self)
+    }
+
+    unsafe fn set_enabled(&self, enabled: bool) -> eyre::Result<()> {
+        if enabled {
+            self.enable()?;
+        } else {
+            self.disable()?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: retour::Function> ToggleableHook for retour::GenericDetour<T> {
+    fn is_enabled(&self) -> bool {
+        retour::GenericDetour::is_enabled(self)
+    }
+
+    unsafe fn set_enabled(&self, enabled: bool) -> eyre::Result<()> {
+        if enabled {
+            self.enable()?;
+        } else {
+            self.disable()?;
+        }
+        Ok(())
+    }
+}
+
+impl ToggleableHook for retour::RawDetour {
+    fn is_enabled(&self) -> bool {
+        retour::RawDetour::is_enabled(self)
+    }
+
+    unsafe fn set_enabled(&self, enabled: bool) -> eyre::Result<()> {
+        if enabled {
+            self.enable()?;
+        } else {
+            self.disable()?;
+        }
+        Ok(())
+    }
+}
+
+/// Named patchers, reverted by [eject] or when the DLL is unloaded. The debug console and crash dumps read them too.
+#[cfg(feature = "patching")]
+pub(crate) static PATCHERS: Mutex<Vec<(String, Arc<Mutex<LocalPatcher>>)>> = Mutex::new(Vec::new());
+/// Named hooks, disabled by [eject] or when the DLL is unloaded. The debug console reads them too.
+pub(crate) static HOOKS: Mutex<Vec<(String, Box<dyn ToggleableHook>)>> = Mutex::new(Vec::new());
+
+/// Register a patcher under the given `name`, replacing any patcher with the same name.
+///
+/// Its patches are reverted before the DLL is unloaded (see [eject]), can be toggled by the `patch` command of the
+/// debug console, and crash dumps mention them when a fault lies within one of them. Patches added to the patcher
+/// after registering are included.
+#[cfg(feature = "patching")]
+pub fn register_patcher(name: impl Into<String>, patcher: Arc<Mutex<LocalPatcher>>) {
+    let name = name.into();
+    let mut patchers = lock(&PATCHERS);
+    patchers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+    patchers.push((name, patcher));
+}
+
+/// Register a hook under the given `name`, replacing any hook with the same name.
+///
+/// It is disabled before the DLL is unloaded (see [eject]), and can be toggled by the `hook` command of the debug
+/// console.
+///
+/// # Example
+/// ```ignore
+/// retour::static_detour! {
+///     static CreateFileHook: unsafe extern "system" fn(PCWSTR, u32) -> HANDLE;
+/// }
+///
+/// register_hook("CreateFile", &CreateFileHook);
+/// ```
+pub fn register_hook(name: impl Into<String>, hook: impl ToggleableHook + 'static) {
+    let name = name.into();
+    let mut hooks = lock(&HOOKS);
+    hooks.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+    hooks.push((name, Box::new(hook)));
+}
+
+/// Unload this DLL from the current process, e.g. in response to a hotkey.
+///
+/// Spawns a dedicated thread which, in order:
+/// 1. Disables the hooks of [raw_input::hooking](crate::raw_input) (with the `hooking-rawinput` feature) and every
+///    hook passed to [register_hook], and reverts the patches of every patcher passed to [register_patcher].
+/// 2. Runs the given `cleanup`.
+/// 3. Signals the [ShutdownToken], waits for `attach` to finish, and runs the `detach` handler of
+///    [dll_main!](crate::dll_main). `detach` is then not run a second time when the DLL is unloaded.
+/// 4. Calls `FreeLibraryAndExitThread` on [get_current_module](crate::get_current_module). If `attach` or its
+///    thread are still running after [DllHandlers::shutdown_timeout] this is reported and the DLL stays loaded
+///    instead, as unmapping it would crash them.
+///
+/// Unregistered hooks have to be disabled by `cleanup` or `detach`, as do any other threads running code in this DLL.
+///
+/// A proxy DLL which was loaded through the import table of the game can't be unloaded.
+pub fn eject(cleanup: impl FnOnce() + Send + 'static) -> eyre::Result<()> {
+    // Only used as a number to cross the thread boundary.
+    let module = crate::get_current_module()?.0 as usize;
+
+    std::thread::Builder::new()
+        .name("eject".into())
+        .spawn(move || {
+            let module = HMODULE(module as *mut c_void);
+
+            disable_hooks_and_patches();
+
+            if let Err(e) = std::panic::catch_unwind(AssertUnwindSafe(cleanup)) {
+                report_panic("eject cleanup", &*e);
+            }
+
+            if let Some(lifecycle) = REGISTERED_LIFECYCLE.get()
+                && !lifecycle.eject_detach(module)
+            {
+                return;
+            }
+
+            unsafe { FreeLibraryAndExitThread(module, 0) }
+        })?;
+
+    Ok(())
+}
+
+/// Disable the hooks and patches we know of, see [eject].
+fn disable_hooks_and_patches() {
+    #[cfg(feature = "hooking-rawinput")]
+    if let Err(e) = unsafe { crate::raw_input::hooking::disable_hooks() } {
+        report_error("disable_hooks", &e);
+    }
+
+    // Taken out, so they're only reverted once when `eject` is followed by `DLL_PROCESS_DETACH`.
+    for (name, hook) in std::mem::take(&mut *lock(&HOOKS)) {
+        if let Err(e) = unsafe { hook.set_enabled(false) } {
+            report_warning(format_args!("Failed to disable hook `{}`: {:?}", name, e));
+        }
+    }
+
+    #[cfg(feature = "patching")]
+    for (_, patcher) in std::mem::take(&mut *lock(&PATCHERS)) {
+        unsafe { lock(&patcher).disable_all_patches() };
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
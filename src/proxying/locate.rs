@@ -0,0 +1,160 @@
+//! Locating and loading the original DLL behind a proxy.
+//!
+//! The original DLL is searched for in the following order:
+//! 1. The environment variable returned by [original_dll_env_var], e.g. `PROXY_DINPUT8_PATH`.
+//! 2. A path registered with [set_original_dll_path].
+//! 3. A renamed copy next to our own DLL, e.g. `dinput8_orig.dll`. Useful for chain-loading another proxy.
+//! 4. The system directory, or `SysWOW64` for 32-bit processes running under WOW64.
+//!
+//! Candidates which point to our own DLL are skipped, as loading those would recurse forever.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, ProxyErrorKind>;
+
+#[derive(Debug, Error)]
+pub enum ProxyErrorKind {
+    #[error("Could not locate the original `{dll_name}`, searched: {searched:?}")]
+    NotFound {
+        dll_name: String,
+        searched: Vec<PathBuf>,
+    },
+
+    #[error("Failed to load the original DLL: {path:?}")]
+    Load {
+        path: PathBuf,
+        #[source]
+        source: libloading::Error,
+    },
+
+    #[error("Missing export `{export}` in `{dll_name}`")]
+    MissingExport {
+        dll_name: &'static str,
+        export: &'static str,
+        #[source]
+        source: libloading::Error,
+    },
+
+    #[error(transparent)]
+    OtherErr(#[from] windows::core::Error),
+
+    #[error(transparent)]
+    Any(#[from] eyre::Error),
+}
+
+static OVERRIDES: Lazy<Mutex<HashMap<String, PathBuf>>> = Lazy::new(Default::default);
+
+/// Load the original `dll_name` from `path` instead of searching for it.
+///
+/// Has to be called before the first call to one of the proxied functions, as the original DLL is only loaded once.
+pub fn set_original_dll_path(dll_name: &str, path: impl Into<PathBuf>) {
+    OVERRIDES
+        .lock()
+        .unwrap()
+        .insert(dll_name.to_ascii_lowercase(), path.into());
+}
+
+/// The environment variable which overrides the location of `dll_name`, e.g. `PROXY_DINPUT8_PATH` for `dinput8.dll`.
+pub fn original_dll_env_var(dll_name: &str) -> String {
+    format!("PROXY_{}_PATH", file_stem(dll_name).to_ascii_uppercase())
+}
+
+/// Find the original `dll_name`, see the [module docs](self) for the search order.
+pub fn locate_original_dll(dll_name: &str) -> Result<PathBuf> {
+    let own_path = crate::get_current_module()
+        .and_then(crate::get_current_dll_path)
+        .ok();
+    let is_own_path = |path: &Path| {
+        own_path
+            .as_deref()
+            .is_some_and(|own| own.as_os_str().eq_ignore_ascii_case(path.as_os_str()))
+    };
+
+    let explicit = std::env::var_os(original_dll_env_var(dll_name))
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(
+            OVERRIDES
+                .lock()
+                .unwrap()
+                .get(&dll_name.to_ascii_lowercase())
+                .cloned(),
+        );
+
+    for path in explicit {
+        if is_own_path(&path) {
+            log::warn!(
+                "Ignoring override for `{}` pointing to ourselves: {:?}",
+                dll_name,
+                path
+            );
+        } else {
+            return Ok(path);
+        }
+    }
+
+    let mut searched = Vec::new();
+
+    if let Some(dir) = own_path.as_deref().and_then(Path::parent) {
+        let renamed = dir.join(format!("{}_orig.dll", file_stem(dll_name)));
+        if renamed.is_file() && !is_own_path(&renamed) {
+            return Ok(renamed);
+        }
+        searched.push(renamed);
+    }
+
+    let system = system_directory()?.join(dll_name);
+    if system.is_file() && !is_own_path(&system) {
+        return Ok(system);
+    }
+    searched.push(system);
+
+    Err(ProxyErrorKind::NotFound {
+        dll_name: dll_name.into(),
+        searched,
+    })
+}
+
+/// Locate and load the original `dll_name`.
+pub fn load_original_dll(dll_name: &str) -> Result<libloading::Library> {
+    let path = locate_original_dll(dll_name)?;
+    log::debug!("Loading `{}` from {:?}", dll_name, path);
+
+    unsafe { libloading::Library::new(&path) }
+        .map_err(|source| ProxyErrorKind::Load { path, source })
+}
+
+/// Called by [proxy_dll!](crate::proxy_dll) exports without a fallback when the original DLL failed to load.
+#[doc(hidden)]
+pub fn unavailable(dll_name: &str, export: &str) -> ! {
+    log::error!(
+        "`{}` was called, but the original `{}` is unavailable, aborting",
+        export,
+        dll_name
+    );
+    std::process::abort()
+}
+
+fn system_directory() -> eyre::Result<PathBuf> {
+    let mut is_wow64 = windows::core::BOOL::default();
+    unsafe {
+        windows::Win32::System::Threading::IsWow64Process(
+            windows::Win32::System::Threading::GetCurrentProcess(),
+            &mut is_wow64,
+        )?
+    };
+
+    if is_wow64.as_bool() {
+        crate::get_system_wow64_directory()
+    } else {
+        crate::get_system_directory()
+    }
+}
+
+fn file_stem(dll_name: &str) -> &str {
+    dll_name.rsplit_once('.').map_or(dll_name, |(stem, _)| stem)
+}
@@ -1,11 +1,50 @@
 pub mod dinput8;
 
+#[cfg(feature = "proxy-d3d9")]
+pub mod d3d9;
+#[cfg(feature = "proxy-dxgi")]
+pub mod dxgi;
+#[cfg(feature = "proxy-version")]
+pub mod version;
+#[cfg(feature = "proxy-winmm")]
+pub mod winmm;
+#[cfg(feature = "proxy-xinput1_3")]
+pub mod xinput1_3;
+
+#[cfg(feature = "proxy")]
+mod generator;
+pub mod lifecycle;
+#[cfg(feature = "proxy")]
+pub mod locate;
+#[cfg(feature = "proxy")]
+pub mod plugins;
+#[cfg(feature = "proxy")]
+pub mod stubs;
+
+/// Re-exports used by [proxy_dll!](crate::proxy_dll) and [lazy_proxy_dll!](crate::lazy_proxy_dll), not part of the public API.
+#[cfg(feature = "proxy")]
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::proxying::locate::unavailable;
+    pub use libloading;
+    pub use libloading::os::windows::Symbol;
+    pub use log;
+    pub use once_cell::sync::Lazy;
+    pub use std::sync::atomic::AtomicUsize;
+}
+
 /// Export a `DllMain` function for the current library.
 ///
 /// This is the standard starting point for creating a proxy DLL.
 ///
-/// Will spawn a new thread for the `attach` function to run in, as well as catch any panics that the `attach`/`detach`
-/// function calls may cause.
+/// By default a new thread is spawned for the `attach` function to run in. Any panics or errors of the handlers are
+/// caught and reported, and never prevent the DLL from loading.
+///
+/// The `detach` function is only called if `attach` succeeded, and only if the DLL is unloaded with `FreeLibrary`.
+/// When the process is terminating all other threads are already gone, and cleaning up is neither safe nor required.
+/// Before `detach` runs the [ShutdownToken](crate::proxying::lifecycle::ShutdownToken) is signalled, and a still
+/// running `attach` is waited on for up to `shutdown_timeout` (5 seconds by default). Remove hooks in `detach`, as it
+/// runs before the module is unmapped.
 ///
 /// # Example
 /// ```norun
@@ -19,67 +58,104 @@ pub mod dinput8;
 ///     Ok(())
 /// }
 ///
-/// rust_hooking_utils::dll_main!(attach, detach)
+/// rust_hooking_utils::dll_main!(attach, detach);
+/// ```
+///
+/// # Extended form
+///
+/// The extended form creates a `state` in `attach` which is handed to `detach`, and optionally handles thread
+/// attach/detach notifications. All keys but `state` and `attach` are optional, but have to be given in this order.
+/// Long-running `attach` functions should poll `context.shutdown` to return early when the DLL is unloaded.
+/// With `attach_mode: AttachMode::Sync` the `attach` function runs directly inside `DllMain`, see
+/// [AttachMode](crate::proxying::lifecycle::AttachMode) for the caveats.
+///
+/// ```norun
+/// use rust_hooking_utils::proxying::lifecycle::{AttachMode, DllContext};
+///
+/// struct Hooks;
+///
+/// fn attach(context: &DllContext) -> eyre::Result<Hooks> {
+///     Ok(Hooks)
+/// }
+///
+/// fn detach(context: &DllContext, hooks: Hooks) -> eyre::Result<()> {
+///     Ok(())
+/// }
+///
+/// fn thread_attach(context: &DllContext) {}
+///
+/// rust_hooking_utils::dll_main! {
+///     state: Hooks,
+///     attach: attach,
+///     detach: detach,
+///     thread_attach: thread_attach,
+///     thread_detach: thread_attach,
+///     attach_mode: AttachMode::Sync,
+///     shutdown_timeout: std::time::Duration::from_secs(1),
+/// }
 /// ```
 #[macro_export]
 macro_rules! dll_main {
-    ($attach:path, $detach:path) => {
+    ($attach:path, $detach:path $(,)?) => {
+        $crate::dll_main! {
+            state: (),
+            attach: |context: &$crate::proxying::lifecycle::DllContext| $attach(context.module),
+            detach: |context: &$crate::proxying::lifecycle::DllContext, _state: ()| $detach(context.module),
+        }
+    };
+    (
+        state: $state:ty,
+        attach: $attach:expr
+        $(, detach: $detach:expr)?
+        $(, thread_attach: $thread_attach:expr)?
+        $(, thread_detach: $thread_detach:expr)?
+        $(, attach_mode: $attach_mode:expr)?
+        $(, shutdown_timeout: $shutdown_timeout:expr)?
+        $(,)?
+    ) => {
         #[unsafe(no_mangle)]
         pub unsafe extern "system" fn DllMain(
             hinst_dll: windows::Win32::Foundation::HMODULE,
             fdw_reason: u32,
             lpv_reserved: *const std::ffi::c_void,
         ) -> i32 {
-            // Hack to get around sending the pointer cross-thread.
-            let hinst_pointer = hinst_dll.0 as usize;
-
-            match fdw_reason {
-                windows::Win32::System::SystemServices::DLL_PROCESS_ATTACH => {
-                    // start loading
-                    let _ =
-                        windows::Win32::System::LibraryLoader::DisableThreadLibraryCalls(hinst_dll);
+            static LIFECYCLE: $crate::proxying::lifecycle::DllLifecycle<$state> =
+                $crate::proxying::lifecycle::DllLifecycle::new($crate::proxying::lifecycle::DllHandlers {
+                    attach: $attach,
+                    detach: $crate::__dll_main_optional!($($detach)?),
+                    thread_attach: $crate::__dll_main_optional!($($thread_attach)?),
+                    thread_detach: $crate::__dll_main_optional!($($thread_detach)?),
+                    attach_mode: $crate::__dll_main_default!(
+                        $crate::proxying::lifecycle::AttachMode::Thread $(, $attach_mode)?
+                    ),
+                    shutdown_timeout: $crate::__dll_main_default!(
+                        $crate::proxying::lifecycle::DEFAULT_SHUTDOWN_TIMEOUT $(, $shutdown_timeout)?
+                    ),
+                });
 
-                    if let Err(e) = std::panic::catch_unwind(|| {
-                        std::thread::spawn(move || {
-                            let hinst = windows::Win32::Foundation::HMODULE(
-                                hinst_pointer as *mut core::ffi::c_void,
-                            );
-                            match $attach(hinst) {
-                                Ok(_) => {}
-                                Err(e) => eprintln!("`dll_attach` returned an Err: {:#?}", e),
-                            }
-                        })
-                    }) {
-                        eprintln!("`dll_attach` has panicked: {:#?}", e);
-                    }
+            LIFECYCLE.dll_main(hinst_dll, fdw_reason, lpv_reserved) as i32
+        }
+    };
+}
 
-                    true as i32
-                }
-                windows::Win32::System::SystemServices::DLL_PROCESS_DETACH => {
-                    // lpv_reserved is null, then we're still in a consistent state and we can clean up safely.
-                    if lpv_reserved.is_null() {
-                        match std::panic::catch_unwind(|| {
-                            let hinst = windows::Win32::Foundation::HMODULE(
-                                hinst_pointer as *mut core::ffi::c_void,
-                            );
-                            $detach(hinst)
-                        }) {
-                            Err(e) => {
-                                eprintln!("`dll_detach` has panicked: {:#?}", e);
-                            }
-                            Ok(r) => match r {
-                                Ok(()) => {}
-                                Err(e) => {
-                                    eprintln!("`dll_detach` returned an Err: {:#?}", e);
-                                }
-                            },
-                        }
-                    }
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __dll_main_optional {
+    () => {
+        None
+    };
+    ($handler:expr) => {
+        Some($handler)
+    };
+}
 
-                    true as i32
-                }
-                _ => true as i32,
-            }
-        }
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __dll_main_default {
+    ($default:expr) => {
+        $default
+    };
+    ($default:expr, $value:expr) => {
+        $value
     };
 }
@@ -0,0 +1,316 @@
+//! Chain-loading of additional plugin DLLs, turning a proxy DLL into a minimal mod loader.
+//!
+//! Plugins are loaded from a folder next to our own DLL, first in the order listed in the optional load order file,
+//! then the remaining DLLs in alphabetical order. After loading, the optional init export of a plugin is called with
+//! a [PluginContext].
+//!
+//! Loading a DLL while holding the loader lock is not allowed, so [load_plugins] should be called from the `attach`
+//! function of [dll_main!](crate::dll_main), which runs on its own thread.
+//!
+//! # Example
+//! ```norun
+//! fn attach(hinst_dll: windows::Win32::Foundation::HMODULE) -> eyre::Result<()> {
+//!     rust_hooking_utils::proxying::plugins::load_plugins(&Default::default())?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! A plugin then exports:
+//! ```norun
+//! #[unsafe(no_mangle)]
+//! pub unsafe extern "system" fn plugin_init(context: *const PluginContext) -> i32 {
+//!     0
+//! }
+//! ```
+use std::ffi::OsStr;
+use std::os::windows::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use windows::Win32::Foundation::HMODULE;
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::core::PCWSTR;
+
+/// The current version of [PluginContext], bumped whenever fields are added.
+pub const PLUGIN_CONTEXT_VERSION: u32 = 1;
+
+/// Signature of the init export, a non-zero return value is logged as an error.
+pub type PluginInitFn = unsafe extern "system" fn(context: *const PluginContext) -> i32;
+
+/// Passed to the init export of every plugin, only valid for the duration of the call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PluginContext {
+    /// Always [PLUGIN_CONTEXT_VERSION] for the current layout.
+    pub version: u32,
+    /// The module of the proxy DLL which loaded the plugin.
+    pub host_module: HMODULE,
+    /// The module of the plugin itself.
+    pub plugin_module: HMODULE,
+    /// The main module of the process, i.e. the game executable.
+    pub game_module: HMODULE,
+    /// The NUL terminated path of the plugin folder.
+    pub plugin_directory: PCWSTR,
+}
+
+#[derive(Debug, Clone)]
+pub struct PluginLoaderConfig {
+    /// The folder to load plugins from, relative paths are resolved against the folder of our own DLL.
+    pub directory: PathBuf,
+    /// The export called after loading a plugin, if present. [None] to skip calling any export.
+    pub init_export: Option<String>,
+    /// A file within [Self::directory] listing plugin file names (one per line, `#` for comments) to load first.
+    pub load_order_file: Option<String>,
+}
+
+impl Default for PluginLoaderConfig {
+    fn default() -> Self {
+        Self {
+            directory: "plugins".into(),
+            init_export: Some("plugin_init".into()),
+            load_order_file: Some("load_order.txt".into()),
+        }
+    }
+}
+
+/// A plugin which was successfully loaded, it stays loaded for the lifetime of the process.
+#[derive(Debug, Clone)]
+pub struct LoadedPlugin {
+    pub name: String,
+    pub path: PathBuf,
+    pub module: usize,
+}
+
+/// Plugins are kept loaded until process exit.
+static LIBRARIES: Mutex<Vec<libloading::Library>> = Mutex::new(Vec::new());
+
+/// Load all plugins according to the given `config`.
+///
+/// A missing plugin folder is not an error. Plugins which fail to load, or whose init export fails, are logged and
+/// skipped, only the successfully initialised plugins are returned.
+pub fn load_plugins(config: &PluginLoaderConfig) -> eyre::Result<Vec<LoadedPlugin>> {
+    let host_module = crate::get_current_module()?;
+    let directory = if config.directory.is_absolute() {
+        config.directory.clone()
+    } else {
+        let own_path = crate::get_current_dll_path(host_module)?;
+        own_path
+            .parent()
+            .ok_or_else(|| eyre::eyre!("Own DLL path has no parent: {:?}", own_path))?
+            .join(&config.directory)
+    };
+
+    if !directory.is_dir() {
+        log::debug!("Plugin directory does not exist: {:?}", directory);
+        return Ok(Vec::new());
+    }
+
+    let order = plugin_load_order(&directory, config.load_order_file.as_deref())?;
+    let game_module = unsafe { GetModuleHandleW(None)? };
+    let directory_wide = to_wide(directory.as_os_str());
+
+    let mut loaded = Vec::with_capacity(order.len());
+
+    for path in order {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let context = PluginContext {
+            version: PLUGIN_CONTEXT_VERSION,
+            host_module,
+            plugin_module: HMODULE::default(),
+            game_module,
+            plugin_directory: PCWSTR(directory_wide.as_ptr()),
+        };
+
+        match load_plugin(&path, config.init_export.as_deref(), context) {
+            Ok(module) => {
+                log::info!("Loaded plugin `{}`", name);
+                loaded.push(LoadedPlugin { name, path, module });
+            }
+            Err(e) => log::error!("Failed to load plugin `{}`: {:?}", name, e),
+        }
+    }
+
+    Ok(loaded)
+}
+
+/// Determine the order in which the DLLs in `directory` are loaded.
+///
+/// DLLs listed in the `load_order_file` come first, in the listed order, followed by all remaining DLLs sorted
+/// alphabetically. Listed files which don't exist are logged and skipped.
+pub fn plugin_load_order(
+    directory: &Path,
+    load_order_file: Option<&str>,
+) -> eyre::Result<Vec<PathBuf>> {
+    let mut remaining = std::fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("dll"))
+        })
+        .collect::<Vec<_>>();
+    remaining.sort_by_key(|path| path.file_name().map(|name| name.to_ascii_lowercase()));
+
+    let mut order = Vec::with_capacity(remaining.len());
+
+    let load_order_path = load_order_file.map(|file| directory.join(file));
+
+    if let Some(load_order_path) = load_order_path.filter(|path| path.is_file()) {
+        let listed = std::fs::read_to_string(&load_order_path)?;
+
+        for name in listed.lines().map(str::trim) {
+            if name.is_empty() || name.starts_with('#') {
+                continue;
+            }
+
+            let position = remaining.iter().position(|path| {
+                path.file_name()
+                    .is_some_and(|file_name| file_name.eq_ignore_ascii_case(name))
+            });
+
+            match position {
+                Some(index) => order.push(remaining.remove(index)),
+                None => log::warn!(
+                    "Plugin listed in {:?} was not found: `{}`",
+                    load_order_path,
+                    name
+                ),
+            }
+        }
+    }
+
+    order.append(&mut remaining);
+
+    Ok(order)
+}
+
+fn load_plugin(
+    path: &Path,
+    init_export: Option<&str>,
+    mut context: PluginContext,
+) -> eyre::Result<usize> {
+    let library = unsafe { libloading::os::windows::Library::new(path)? };
+    // Round-trip through the raw handle to get hold of the `HMODULE`.
+    let raw_module = library.into_raw();
+    let library = unsafe { libloading::os::windows::Library::from_raw(raw_module) };
+    context.plugin_module = HMODULE(raw_module as *mut _);
+
+    let init = match init_export {
+        Some(init_export) => match unsafe { library.get::<PluginInitFn>(init_export.as_bytes()) } {
+            Ok(init) => Some((init_export, *init)),
+            Err(_) => {
+                log::debug!("Plugin {:?} has no `{}` export", path, init_export);
+                None
+            }
+        },
+        None => None,
+    };
+
+    // Stays loaded even if the init export fails, as it might have already spawned threads or installed hooks.
+    LIBRARIES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(library.into());
+
+    if let Some((init_export, init)) = init {
+        let status = unsafe { init(&context) };
+        if status != 0 {
+            eyre::bail!("`{}` returned {}", init_export, status);
+        }
+    }
+
+    Ok(raw_module as usize)
+}
+
+fn to_wide(value: &OsStr) -> Vec<u16> {
+    value.encode_wide().chain(std::iter::once(0)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::plugin_load_order;
+
+    /// A fresh folder in the temp directory containing empty `files`, removed on drop.
+    struct PluginDir(PathBuf);
+
+    impl PluginDir {
+        fn new(name: &str, files: &[&str]) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "rust_hooking_utils_plugins_{}_{}",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            for file in files {
+                std::fs::write(path.join(file), b"").unwrap();
+            }
+            Self(path)
+        }
+    }
+
+    impl Drop for PluginDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn load_order(dir: &Path, load_order_file: Option<&str>) -> Vec<String> {
+        plugin_load_order(dir, load_order_file)
+            .unwrap()
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn sorts_alphabetically_without_load_order_file() {
+        let dir = PluginDir::new("alphabetical", &["b.dll", "C.DLL", "a.dll", "readme.txt"]);
+        std::fs::create_dir(dir.0.join("folder.dll")).unwrap();
+
+        assert_eq!(load_order(&dir.0, None), ["a.dll", "b.dll", "C.DLL"]);
+        // A configured but missing file is the same as none.
+        assert_eq!(
+            load_order(&dir.0, Some("load_order.txt")),
+            ["a.dll", "b.dll", "C.DLL"]
+        );
+    }
+
+    #[test]
+    fn listed_plugins_come_first() {
+        let dir = PluginDir::new("listed", &["a.dll", "b.dll", "c.dll", "d.dll"]);
+        std::fs::write(dir.0.join("load_order.txt"), "c.dll\nA.DLL\n").unwrap();
+
+        assert_eq!(
+            load_order(&dir.0, Some("load_order.txt")),
+            ["c.dll", "a.dll", "b.dll", "d.dll"]
+        );
+        // The file is only used when configured.
+        assert_eq!(
+            load_order(&dir.0, None),
+            ["a.dll", "b.dll", "c.dll", "d.dll"]
+        );
+    }
+
+    #[test]
+    fn skips_comments_blank_lines_and_missing_plugins() {
+        let dir = PluginDir::new("comments", &["a.dll", "b.dll", "c.dll"]);
+        std::fs::write(
+            dir.0.join("order.txt"),
+            "# Load the framework first\n\n  b.dll  \r\nmissing.dll\n#c.dll\nb.dll\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            load_order(&dir.0, Some("order.txt")),
+            ["b.dll", "a.dll", "c.dll"]
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//! Runtime support for [lazy_proxy_dll!](crate::lazy_proxy_dll).
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use once_cell::sync::OnceCell;
+
+/// Lazily loads the original DLL and resolves exports into a pointer table on first use.
+///
+/// Every generated stub first checks its slot in the table, and only calls into [Self::resolve] if it is still empty.
+pub struct LazyProxy {
+    dll_name: &'static str,
+    exports: &'static [&'static str],
+    slots: &'static [AtomicUsize],
+    library: OnceCell<libloading::Library>,
+}
+
+impl LazyProxy {
+    pub const fn new(
+        dll_name: &'static str,
+        exports: &'static [&'static str],
+        slots: &'static [AtomicUsize],
+    ) -> Self {
+        Self {
+            dll_name,
+            exports,
+            slots,
+            library: OnceCell::new(),
+        }
+    }
+
+    /// The file name of the proxied DLL.
+    pub fn dll_name(&self) -> &'static str {
+        self.dll_name
+    }
+
+    /// The names of all proxied exports, indexed by their slot.
+    pub fn exports(&self) -> &'static [&'static str] {
+        self.exports
+    }
+
+    /// Resolve the export at `index`, aborting the process on failure.
+    ///
+    /// Called from the generated stubs, at which point there is no sensible way to report an error to the caller.
+    pub fn resolve(&self, index: usize) -> usize {
+        match self.try_resolve(index) {
+            Ok(address) => address,
+            Err(e) => {
+                log::error!(
+                    "Failed to resolve `{}` in `{}`: {:#}",
+                    self.exports.get(index).unwrap_or(&"<unknown>"),
+                    self.dll_name,
+                    e
+                );
+                std::process::abort()
+            }
+        }
+    }
+
+    /// Resolve the export at `index` and store it in its slot, loading the original DLL if required.
+    pub fn try_resolve(&self, index: usize) -> eyre::Result<usize> {
+        let slot = self
+            .slots
+            .get(index)
+            .ok_or_else(|| eyre::eyre!("Export index out of bounds: {}", index))?;
+
+        let address = slot.load(Ordering::Acquire);
+        if address != 0 {
+            return Ok(address);
+        }
+
+        let library = self
+            .library
+            .get_or_try_init(|| crate::proxying::locate::load_original_dll(self.dll_name))?;
+
+        let name = self.exports[index];
+        let address = unsafe {
+            let symbol = library.get::<unsafe extern "system" fn()>(name.as_bytes())?;
+            *symbol as usize
+        };
+        log::trace!("Resolved `{}` to {:#X}", name, address);
+
+        slot.store(address, Ordering::Release);
+
+        Ok(address)
+    }
+}
@@ -0,0 +1,69 @@
+//! Contains a pre-made proxy for version.dll
+//!
+//! Enabling the `proxy-version` feature will automatically export the pre-requisite functions if the parent crate is
+//! compiled as a `crate-type = ["cdylib"]`.
+//!
+//! The undocumented `GetFileVersionInfoByHandle` export is not forwarded. `VerFindFile*` and `VerInstallFile*` have no
+//! sensible fallback, and abort the process if the original DLL can't be loaded.
+
+use std::ffi::c_void;
+
+use windows::core::{BOOL, PCSTR, PCWSTR};
+
+crate::proxy_dll! {
+    pub struct VersionProxyManager("version.dll");
+    pub static VERSION_MANAGER;
+
+    fn GetFileVersionInfoA(filename: PCSTR, handle: u32, len: u32, data: *mut c_void) -> BOOL = BOOL(0);
+    fn GetFileVersionInfoExA(flags: u32, filename: PCSTR, handle: u32, len: u32, data: *mut c_void) -> BOOL = BOOL(0);
+    fn GetFileVersionInfoExW(flags: u32, filename: PCWSTR, handle: u32, len: u32, data: *mut c_void) -> BOOL = BOOL(0);
+    fn GetFileVersionInfoSizeA(filename: PCSTR, handle: *mut u32) -> u32 = 0;
+    fn GetFileVersionInfoSizeExA(flags: u32, filename: PCSTR, handle: *mut u32) -> u32 = 0;
+    fn GetFileVersionInfoSizeExW(flags: u32, filename: PCWSTR, handle: *mut u32) -> u32 = 0;
+    fn GetFileVersionInfoSizeW(filename: PCWSTR, handle: *mut u32) -> u32 = 0;
+    fn GetFileVersionInfoW(filename: PCWSTR, handle: u32, len: u32, data: *mut c_void) -> BOOL = BOOL(0);
+    fn VerFindFileA(
+        flags: u32,
+        file_name: PCSTR,
+        win_dir: PCSTR,
+        app_dir: PCSTR,
+        cur_dir: *mut u8,
+        cur_dir_len: *mut u32,
+        dest_dir: *mut u8,
+        dest_dir_len: *mut u32,
+    ) -> u32;
+    fn VerFindFileW(
+        flags: u32,
+        file_name: PCWSTR,
+        win_dir: PCWSTR,
+        app_dir: PCWSTR,
+        cur_dir: *mut u16,
+        cur_dir_len: *mut u32,
+        dest_dir: *mut u16,
+        dest_dir_len: *mut u32,
+    ) -> u32;
+    fn VerInstallFileA(
+        flags: u32,
+        src_file_name: PCSTR,
+        dest_file_name: PCSTR,
+        src_dir: PCSTR,
+        dest_dir: PCSTR,
+        cur_dir: PCSTR,
+        tmp_file: *mut u8,
+        tmp_file_len: *mut u32,
+    ) -> u32;
+    fn VerInstallFileW(
+        flags: u32,
+        src_file_name: PCWSTR,
+        dest_file_name: PCWSTR,
+        src_dir: PCWSTR,
+        dest_dir: PCWSTR,
+        cur_dir: PCWSTR,
+        tmp_file: *mut u16,
+        tmp_file_len: *mut u32,
+    ) -> u32;
+    fn VerLanguageNameA(lang: u32, lang_name: *mut u8, size: u32) -> u32 = 0;
+    fn VerLanguageNameW(lang: u32, lang_name: *mut u16, size: u32) -> u32 = 0;
+    fn VerQueryValueA(block: *const c_void, sub_block: PCSTR, buffer: *mut *mut c_void, len: *mut u32) -> BOOL = BOOL(0);
+    fn VerQueryValueW(block: *const c_void, sub_block: PCWSTR, buffer: *mut *mut c_void, len: *mut u32) -> BOOL = BOOL(0);
+}
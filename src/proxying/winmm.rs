@@ -0,0 +1,215 @@
+//! Contains a pre-made proxy for winmm.dll
+//!
+//! Enabling the `proxy-winmm` feature will automatically export the pre-requisite functions if the parent crate is
+//! compiled as a `crate-type = ["cdylib"]`.
+//!
+//! Only the documented application-facing exports are forwarded, the undocumented and driver-side ones
+//! (`mmTask*`, `mciDriverNotify`, `DriverCallback`, `WOWAppExit`, ...) are not. If the original DLL can't be loaded
+//! every export reports an error (`MMSYSERR_NODRIVER`, a null handle or zero devices), apart from `timeGetTime` which
+//! falls back to `GetTickCount`.
+
+use std::ffi::c_void;
+
+use windows::Win32::System::SystemInformation::GetTickCount;
+use windows::core::{BOOL, PCSTR, PCWSTR};
+
+const MMSYSERR_NODRIVER: u32 = 6;
+const MCIERR_DEVICE_NOT_INSTALLED: u32 = 256 + 50;
+
+crate::proxy_dll! {
+    pub struct WinmmProxyManager("winmm.dll");
+    pub static WINMM_MANAGER;
+
+    fn CloseDriver(driver: *mut c_void, param1: isize, param2: isize) -> isize = 0;
+    fn GetDriverModuleHandle(driver: *mut c_void) -> *mut c_void = std::ptr::null_mut();
+    fn OpenDriver(driver_name: PCWSTR, section_name: PCWSTR, param: isize) -> *mut c_void = std::ptr::null_mut();
+    fn PlaySound(sound: PCSTR, module: *mut c_void, flags: u32) -> BOOL = BOOL(0);
+    fn PlaySoundA(sound: PCSTR, module: *mut c_void, flags: u32) -> BOOL = BOOL(0);
+    fn PlaySoundW(sound: PCWSTR, module: *mut c_void, flags: u32) -> BOOL = BOOL(0);
+    fn SendDriverMessage(driver: *mut c_void, message: u32, param1: isize, param2: isize) -> isize = 0;
+    fn auxGetDevCapsA(device_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn auxGetDevCapsW(device_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn auxGetNumDevs() -> u32 = 0;
+    fn auxGetVolume(device_id: u32, volume: *mut u32) -> u32 = MMSYSERR_NODRIVER;
+    fn auxOutMessage(device_id: u32, message: u32, param1: usize, param2: usize) -> u32 = MMSYSERR_NODRIVER;
+    fn auxSetVolume(device_id: u32, volume: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn joyConfigChanged(flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn joyGetDevCapsA(joy_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn joyGetDevCapsW(joy_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn joyGetNumDevs() -> u32 = 0;
+    fn joyGetPos(joy_id: u32, info: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn joyGetPosEx(joy_id: u32, info: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn joyGetThreshold(joy_id: u32, threshold: *mut u32) -> u32 = MMSYSERR_NODRIVER;
+    fn joyReleaseCapture(joy_id: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn joySetCapture(window: *mut c_void, joy_id: u32, period: u32, changed: BOOL) -> u32 = MMSYSERR_NODRIVER;
+    fn joySetThreshold(joy_id: u32, threshold: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mciExecute(command: PCSTR) -> BOOL = BOOL(0);
+    fn mciGetCreatorTask(device_id: u32) -> *mut c_void = std::ptr::null_mut();
+    fn mciGetDeviceIDA(device: PCSTR) -> u32 = 0;
+    fn mciGetDeviceIDW(device: PCWSTR) -> u32 = 0;
+    fn mciGetErrorStringA(error: u32, text: *mut u8, len: u32) -> BOOL = BOOL(0);
+    fn mciGetErrorStringW(error: u32, text: *mut u16, len: u32) -> BOOL = BOOL(0);
+    fn mciGetYieldProc(device_id: u32, yield_data: *mut u32) -> *mut c_void = std::ptr::null_mut();
+    fn mciSendCommandA(device_id: u32, message: u32, param1: usize, param2: usize) -> u32 = MCIERR_DEVICE_NOT_INSTALLED;
+    fn mciSendCommandW(device_id: u32, message: u32, param1: usize, param2: usize) -> u32 = MCIERR_DEVICE_NOT_INSTALLED;
+    fn mciSendStringA(command: PCSTR, return_string: *mut u8, return_len: u32, callback: *mut c_void) -> u32 =
+        MCIERR_DEVICE_NOT_INSTALLED;
+    fn mciSendStringW(command: PCWSTR, return_string: *mut u16, return_len: u32, callback: *mut c_void) -> u32 =
+        MCIERR_DEVICE_NOT_INSTALLED;
+    fn mciSetYieldProc(device_id: u32, yield_proc: *mut c_void, yield_data: u32) -> BOOL = BOOL(0);
+    fn midiConnect(midi: *mut c_void, midi_out: *mut c_void, reserved: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn midiDisconnect(midi: *mut c_void, midi_out: *mut c_void, reserved: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn midiInAddBuffer(midi_in: *mut c_void, header: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiInClose(midi_in: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn midiInGetDevCapsA(device_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiInGetDevCapsW(device_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiInGetErrorTextA(error: u32, text: *mut u8, len: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiInGetErrorTextW(error: u32, text: *mut u16, len: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiInGetID(midi_in: *mut c_void, device_id: *mut u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiInGetNumDevs() -> u32 = 0;
+    fn midiInMessage(midi_in: *mut c_void, message: u32, param1: usize, param2: usize) -> u32 = MMSYSERR_NODRIVER;
+    fn midiInOpen(midi_in: *mut *mut c_void, device_id: u32, callback: usize, instance: usize, flags: u32) -> u32 =
+        MMSYSERR_NODRIVER;
+    fn midiInPrepareHeader(midi_in: *mut c_void, header: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiInReset(midi_in: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn midiInStart(midi_in: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn midiInStop(midi_in: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn midiInUnprepareHeader(midi_in: *mut c_void, header: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutCacheDrumPatches(midi_out: *mut c_void, patch: u32, keys: *mut u16, flags: u32) -> u32 =
+        MMSYSERR_NODRIVER;
+    fn midiOutCachePatches(midi_out: *mut c_void, bank: u32, patches: *mut u16, flags: u32) -> u32 =
+        MMSYSERR_NODRIVER;
+    fn midiOutClose(midi_out: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutGetDevCapsA(device_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutGetDevCapsW(device_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutGetErrorTextA(error: u32, text: *mut u8, len: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutGetErrorTextW(error: u32, text: *mut u16, len: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutGetID(midi_out: *mut c_void, device_id: *mut u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutGetNumDevs() -> u32 = 0;
+    fn midiOutGetVolume(midi_out: *mut c_void, volume: *mut u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutLongMsg(midi_out: *mut c_void, header: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutMessage(midi_out: *mut c_void, message: u32, param1: usize, param2: usize) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutOpen(midi_out: *mut *mut c_void, device_id: u32, callback: usize, instance: usize, flags: u32) -> u32 =
+        MMSYSERR_NODRIVER;
+    fn midiOutPrepareHeader(midi_out: *mut c_void, header: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutReset(midi_out: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutSetVolume(midi_out: *mut c_void, volume: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutShortMsg(midi_out: *mut c_void, message: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiOutUnprepareHeader(midi_out: *mut c_void, header: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiStreamClose(stream: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn midiStreamOpen(
+        stream: *mut *mut c_void,
+        device_id: *mut u32,
+        c_midi: u32,
+        callback: usize,
+        instance: usize,
+        flags: u32,
+    ) -> u32 = MMSYSERR_NODRIVER;
+    fn midiStreamOut(stream: *mut c_void, header: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiStreamPause(stream: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn midiStreamPosition(stream: *mut c_void, time: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiStreamProperty(stream: *mut c_void, property_data: *mut u8, property: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn midiStreamRestart(stream: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn midiStreamStop(stream: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn mixerClose(mixer: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn mixerGetControlDetailsA(mixer: *mut c_void, details: *mut c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mixerGetControlDetailsW(mixer: *mut c_void, details: *mut c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mixerGetDevCapsA(mixer_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mixerGetDevCapsW(mixer_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mixerGetID(mixer: *mut c_void, mixer_id: *mut u32, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mixerGetLineControlsA(mixer: *mut c_void, controls: *mut c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mixerGetLineControlsW(mixer: *mut c_void, controls: *mut c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mixerGetLineInfoA(mixer: *mut c_void, line: *mut c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mixerGetLineInfoW(mixer: *mut c_void, line: *mut c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mixerGetNumDevs() -> u32 = 0;
+    fn mixerMessage(mixer: *mut c_void, message: u32, param1: usize, param2: usize) -> u32 = MMSYSERR_NODRIVER;
+    fn mixerOpen(mixer: *mut *mut c_void, mixer_id: u32, callback: usize, instance: usize, flags: u32) -> u32 =
+        MMSYSERR_NODRIVER;
+    fn mixerSetControlDetails(mixer: *mut c_void, details: *mut c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mmioAdvance(mmio: *mut c_void, info: *mut c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mmioAscend(mmio: *mut c_void, chunk: *mut c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mmioClose(mmio: *mut c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mmioCreateChunk(mmio: *mut c_void, chunk: *mut c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mmioDescend(mmio: *mut c_void, chunk: *mut c_void, parent: *const c_void, flags: u32) -> u32 =
+        MMSYSERR_NODRIVER;
+    fn mmioFlush(mmio: *mut c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mmioGetInfo(mmio: *mut c_void, info: *mut c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mmioInstallIOProcA(four_cc: u32, io_proc: *mut c_void, flags: u32) -> *mut c_void = std::ptr::null_mut();
+    fn mmioInstallIOProcW(four_cc: u32, io_proc: *mut c_void, flags: u32) -> *mut c_void = std::ptr::null_mut();
+    fn mmioOpenA(filename: *mut u8, info: *mut c_void, flags: u32) -> *mut c_void = std::ptr::null_mut();
+    fn mmioOpenW(filename: *mut u16, info: *mut c_void, flags: u32) -> *mut c_void = std::ptr::null_mut();
+    fn mmioRead(mmio: *mut c_void, buffer: *mut u8, len: i32) -> i32 = -1;
+    fn mmioRenameA(filename: PCSTR, new_filename: PCSTR, info: *const c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mmioRenameW(filename: PCWSTR, new_filename: PCWSTR, info: *const c_void, flags: u32) -> u32 =
+        MMSYSERR_NODRIVER;
+    fn mmioSeek(mmio: *mut c_void, offset: i32, origin: i32) -> i32 = -1;
+    fn mmioSendMessage(mmio: *mut c_void, message: u32, param1: isize, param2: isize) -> isize = 0;
+    fn mmioSetBuffer(mmio: *mut c_void, buffer: *mut u8, len: i32, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mmioSetInfo(mmio: *mut c_void, info: *const c_void, flags: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn mmioStringToFOURCCA(string: PCSTR, flags: u32) -> u32 = 0;
+    fn mmioStringToFOURCCW(string: PCWSTR, flags: u32) -> u32 = 0;
+    fn mmioWrite(mmio: *mut c_void, buffer: *const u8, len: i32) -> i32 = -1;
+    fn mmsystemGetVersion() -> u32 = 0;
+    fn sndPlaySoundA(sound: PCSTR, flags: u32) -> BOOL = BOOL(0);
+    fn sndPlaySoundW(sound: PCWSTR, flags: u32) -> BOOL = BOOL(0);
+    fn timeBeginPeriod(period: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn timeEndPeriod(period: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn timeGetDevCaps(caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn timeGetSystemTime(time: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn timeGetTime() -> u32 = unsafe { GetTickCount() };
+    fn timeKillEvent(timer_id: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn timeSetEvent(delay: u32, resolution: u32, callback: *mut c_void, user: usize, event: u32) -> u32 = 0;
+    fn waveInAddBuffer(wave_in: *mut c_void, header: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInClose(wave_in: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInGetDevCapsA(device_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInGetDevCapsW(device_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInGetErrorTextA(error: u32, text: *mut u8, len: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInGetErrorTextW(error: u32, text: *mut u16, len: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInGetID(wave_in: *mut c_void, device_id: *mut u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInGetNumDevs() -> u32 = 0;
+    fn waveInGetPosition(wave_in: *mut c_void, time: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInMessage(wave_in: *mut c_void, message: u32, param1: usize, param2: usize) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInOpen(
+        wave_in: *mut *mut c_void,
+        device_id: u32,
+        format: *const c_void,
+        callback: usize,
+        instance: usize,
+        flags: u32,
+    ) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInPrepareHeader(wave_in: *mut c_void, header: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInReset(wave_in: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInStart(wave_in: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInStop(wave_in: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn waveInUnprepareHeader(wave_in: *mut c_void, header: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutBreakLoop(wave_out: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutClose(wave_out: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutGetDevCapsA(device_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutGetDevCapsW(device_id: usize, caps: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutGetErrorTextA(error: u32, text: *mut u8, len: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutGetErrorTextW(error: u32, text: *mut u16, len: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutGetID(wave_out: *mut c_void, device_id: *mut u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutGetNumDevs() -> u32 = 0;
+    fn waveOutGetPitch(wave_out: *mut c_void, pitch: *mut u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutGetPlaybackRate(wave_out: *mut c_void, rate: *mut u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutGetPosition(wave_out: *mut c_void, time: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutGetVolume(wave_out: *mut c_void, volume: *mut u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutMessage(wave_out: *mut c_void, message: u32, param1: usize, param2: usize) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutOpen(
+        wave_out: *mut *mut c_void,
+        device_id: u32,
+        format: *const c_void,
+        callback: usize,
+        instance: usize,
+        flags: u32,
+    ) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutPause(wave_out: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutPrepareHeader(wave_out: *mut c_void, header: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutReset(wave_out: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutRestart(wave_out: *mut c_void) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutSetPitch(wave_out: *mut c_void, pitch: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutSetPlaybackRate(wave_out: *mut c_void, rate: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutSetVolume(wave_out: *mut c_void, volume: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutUnprepareHeader(wave_out: *mut c_void, header: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+    fn waveOutWrite(wave_out: *mut c_void, header: *mut c_void, size: u32) -> u32 = MMSYSERR_NODRIVER;
+}
@@ -0,0 +1,29 @@
+//! Contains a pre-made proxy for xinput1_3.dll
+//!
+//! Enabling the `proxy-xinput1_3` feature will automatically export the pre-requisite functions if the parent crate is
+//! compiled as a `crate-type = ["cdylib"]`.
+//!
+//! The ordinal-only exports (e.g. `XInputGetStateEx` at ordinal `100`) are not forwarded.
+//!
+//! # Note
+//!
+//! This proxy exports `XInputGetState`, which collides with [crate::raw_input::hooking::hook_xinput_get_state] and
+//! the XInput calls in [crate::raw_input::key_manager]. Those will end up calling this proxy instead of the system DLL.
+
+use std::ffi::c_void;
+
+use windows::Win32::Foundation::ERROR_DEVICE_NOT_CONNECTED;
+use windows::core::{BOOL, GUID};
+
+crate::proxy_dll! {
+    pub struct XInputProxyManager("xinput1_3.dll");
+    pub static XINPUT_MANAGER;
+
+    fn XInputEnable(enable: BOOL) = ();
+    fn XInputGetBatteryInformation(user_index: u32, dev_type: u8, battery_information: *mut c_void) -> u32 = ERROR_DEVICE_NOT_CONNECTED.0;
+    fn XInputGetCapabilities(user_index: u32, flags: u32, capabilities: *mut c_void) -> u32 = ERROR_DEVICE_NOT_CONNECTED.0;
+    fn XInputGetDSoundAudioDeviceGuids(user_index: u32, render_guid: *mut GUID, capture_guid: *mut GUID) -> u32 = ERROR_DEVICE_NOT_CONNECTED.0;
+    fn XInputGetKeystroke(user_index: u32, reserved: u32, keystroke: *mut c_void) -> u32 = ERROR_DEVICE_NOT_CONNECTED.0;
+    fn XInputGetState(user_index: u32, state: *mut c_void) -> u32 = ERROR_DEVICE_NOT_CONNECTED.0;
+    fn XInputSetState(user_index: u32, vibration: *mut c_void) -> u32 = ERROR_DEVICE_NOT_CONNECTED.0;
+}
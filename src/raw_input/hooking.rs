@@ -1,5 +1,6 @@
 use retour::static_detour;
 use std::ffi::c_void;
+use std::panic::AssertUnwindSafe;
 use windows::Win32::Foundation::HWND;
 use windows::core::HRESULT;
 
@@ -12,6 +13,8 @@ pub struct RAWINPUTDEVICE {
     pub hwnd_target: HWND,
 }
 
+use crate::reporting::report_panic;
+
 static_detour! {
     pub static D_GET_RAW_INPUT_DATA: extern "system" fn(isize, u32, *mut c_void, *mut u32, u32) -> HRESULT;
     pub static D_REGISTER_RAW_INPUT_DEV: extern "system" fn(*const RAWINPUTDEVICE, u32, u32) -> windows::core::BOOL;
@@ -33,7 +36,18 @@ pub unsafe fn hook_raw_input_data(
         ) -> u32;
     }
 
-    D_GET_RAW_INPUT_DATA.initialize(std::mem::transmute(GetRawInputData as *const c_void), hook)?;
+    D_GET_RAW_INPUT_DATA.initialize(
+        std::mem::transmute(GetRawInputData as *const c_void),
+        move |hrawinput, uicommand, pdata, pcbsize, cbsizeheader| {
+            std::panic::catch_unwind(AssertUnwindSafe(|| {
+                hook(hrawinput, uicommand, pdata, pcbsize, cbsizeheader)
+            }))
+            .unwrap_or_else(|e| {
+                report_panic("GetRawInputData hook", &*e);
+                D_GET_RAW_INPUT_DATA.call(hrawinput, uicommand, pdata, pcbsize, cbsizeheader)
+            })
+        },
+    )?;
 
     D_GET_RAW_INPUT_DATA.enable()?;
 
@@ -54,7 +68,13 @@ pub unsafe fn hook_register_raw_input(
 
     D_REGISTER_RAW_INPUT_DEV.initialize(
         std::mem::transmute(RegisterRawInputDevices as *const c_void),
-        hook,
+        move |devices, num_devices, size| {
+            std::panic::catch_unwind(AssertUnwindSafe(|| hook(devices, num_devices, size)))
+                .unwrap_or_else(|e| {
+                    report_panic("RegisterRawInputDevices hook", &*e);
+                    D_REGISTER_RAW_INPUT_DEV.call(devices, num_devices, size)
+                })
+        },
     )?;
 
     D_REGISTER_RAW_INPUT_DEV.enable()?;
@@ -72,10 +92,40 @@ pub unsafe fn hook_xinput_get_state(
 
     D_XINPUT_GET_STATE.initialize(
         std::mem::transmute(XInputGetState as *const c_void),
-        hook,
+        move |user_index, state| {
+            std::panic::catch_unwind(AssertUnwindSafe(|| hook(user_index, state))).unwrap_or_else(
+                |e| {
+                    report_panic("XInputGetState hook", &*e);
+                    D_XINPUT_GET_STATE.call(user_index, state)
+                },
+            )
+        },
     )?;
 
     D_XINPUT_GET_STATE.enable()?;
 
     Ok(())
 }
+
+/// Disable all of the above hooks which are currently enabled.
+///
+/// They can be re-enabled by calling `enable()` on the respective detour.
+///
+/// # Safety
+///
+/// Restores the original code of the hooked functions, no other thread may be executing their prologue meanwhile.
+pub unsafe fn disable_hooks() -> eyre::Result<()> {
+    if D_GET_RAW_INPUT_DATA.is_enabled() {
+        D_GET_RAW_INPUT_DATA.disable()?;
+    }
+
+    if D_REGISTER_RAW_INPUT_DEV.is_enabled() {
+        D_REGISTER_RAW_INPUT_DEV.disable()?;
+    }
+
+    if D_XINPUT_GET_STATE.is_enabled() {
+        D_XINPUT_GET_STATE.disable()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,191 @@
+//! Reporting of errors and panics for injected DLLs, where there usually is no console to print to.
+//!
+//! Until [install_reporter] is called everything is only printed to stderr. Afterwards reports are also appended to
+//! a log file next to our own DLL, and can optionally be shown in a message box.
+//!
+//! # Example
+//! ```norun
+//! fn attach(hinst_dll: windows::Win32::Foundation::HMODULE) -> eyre::Result<()> {
+//!     rust_hooking_utils::reporting::install_reporter(ReporterConfig {
+//!         message_box: true,
+//!         ..Default::default()
+//!     })?;
+//!     Ok(())
+//! }
+//! ```
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::fmt::Display;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use windows::Win32::System::SystemInformation::GetLocalTime;
+use windows::Win32::UI::WindowsAndMessaging::{MB_ICONERROR, MB_OK, MessageBoxW};
+use windows::core::HSTRING;
+
+#[derive(Debug, Clone)]
+pub struct ReporterConfig {
+    /// The file reports are appended to, relative paths are resolved against the folder of our own DLL.
+    /// Defaults to our own DLL path with a `.log` extension, [None] disables the log file.
+    pub log_file: Option<PathBuf>,
+    /// Show errors and panics in a message box.
+    ///
+    /// Note that showing a message box while holding the loader lock (e.g. in a synchronous `attach`) can deadlock.
+    pub message_box: bool,
+    /// Capture a backtrace for panics, regardless of `RUST_BACKTRACE`.
+    pub capture_backtrace: bool,
+    /// The title of the message box, defaults to the file name of our own DLL.
+    pub title: Option<String>,
+}
+
+impl Default for ReporterConfig {
+    fn default() -> Self {
+        Self {
+            log_file: crate::get_current_module()
+                .and_then(crate::get_current_dll_path)
+                .ok()
+                .map(|path| path.with_extension("log")),
+            message_box: false,
+            capture_backtrace: true,
+            title: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Reporter {
+    log_file: Option<PathBuf>,
+    message_box: bool,
+    capture_backtrace: bool,
+    title: String,
+}
+
+static REPORTER: RwLock<Option<Reporter>> = RwLock::new(None);
+
+/// Install the reporter, and a panic hook which reports all panics before calling the previous hook.
+///
+/// Calling it again replaces the configuration, the panic hook is only installed once.
+pub fn install_reporter(config: ReporterConfig) -> eyre::Result<()> {
+    let dll_path = crate::get_current_dll_path(crate::get_current_module()?)?;
+    let dll_name = dll_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let log_file = config.log_file.map(|log_file| match dll_path.parent() {
+        Some(dir) if log_file.is_relative() => dir.join(log_file),
+        _ => log_file,
+    });
+
+    let reporter = Reporter {
+        log_file,
+        message_box: config.message_box,
+        capture_backtrace: config.capture_backtrace,
+        title: config.title.unwrap_or(dll_name),
+    };
+
+    let previous = REPORTER
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .replace(reporter);
+
+    if previous.is_none() {
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let backtrace = REPORTER
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .as_ref()
+                .filter(|reporter| reporter.capture_backtrace)
+                .map(|_| Backtrace::force_capture());
+
+            let mut message = format!("Panic: {}", info);
+            if let Some(backtrace) = backtrace {
+                message.push_str(&format!("\nBacktrace:\n{}", backtrace));
+            }
+
+            report(&message, true);
+            previous_hook(info);
+        }));
+    }
+
+    Ok(())
+}
+
+/// Report an error returned from `context`, e.g. a `dll_attach` function.
+pub fn report_error(context: &str, error: &eyre::Report) {
+    report(&format!("`{}` returned an Err: {:?}", context, error), true);
+}
+
+/// Report a panic caught with [std::panic::catch_unwind] in `context`.
+///
+/// If the reporter is installed the panic itself was already reported by its panic hook, so only a short note is
+/// written.
+pub fn report_panic(context: &str, payload: &(dyn Any + Send)) {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("<non-string payload>");
+    let hook_installed = REPORTER.read().unwrap_or_else(|e| e.into_inner()).is_some();
+
+    report(
+        &format!("`{}` has panicked: {}", context, message),
+        !hook_installed,
+    );
+}
+
+/// Report a non-fatal problem, never shown in a message box.
+pub fn report_warning(message: impl Display) {
+    report(&message.to_string(), false);
+}
+
+fn report(message: &str, show_message_box: bool) {
+    eprintln!("{}", message);
+
+    let reporter = REPORTER.read().unwrap_or_else(|e| e.into_inner());
+    let Some(reporter) = reporter.as_ref() else {
+        return;
+    };
+
+    if let Some(log_file) = &reporter.log_file {
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_file)
+            .and_then(|mut file| writeln!(file, "[{}] {}", local_timestamp(), message));
+
+        if let Err(e) = written {
+            eprintln!("Failed to write to {:?}: {}", log_file, e);
+        }
+    }
+
+    if show_message_box && reporter.message_box {
+        unsafe {
+            MessageBoxW(
+                None,
+                &HSTRING::from(message),
+                &HSTRING::from(reporter.title.as_str()),
+                MB_OK | MB_ICONERROR,
+            );
+        }
+    }
+}
+
+/// The current local time as `YYYY-MM-DD hh:mm:ss.mmm`.
+pub(crate) fn local_timestamp() -> String {
+    let time = unsafe { GetLocalTime() };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        time.wYear,
+        time.wMonth,
+        time.wDay,
+        time.wHour,
+        time.wMinute,
+        time.wSecond,
+        time.wMilliseconds
+    )
+}